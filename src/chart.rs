@@ -0,0 +1,951 @@
+use std::{ops::Range, slice};
+
+use clap::ValueEnum;
+
+use crate::{
+    dataset::{Columns, Dataset},
+    group::GroupAggregate,
+    stats::{self, Bin, BinRule, MAX_BINS},
+};
+
+/// Plot size in characters when the terminal size is unknown.
+pub const GRAPH_HEIGHT: usize = 15;
+pub const GRAPH_WIDTH: usize = 40;
+
+/// Smallest plot the axis labels fit around.
+const MIN_WIDTH: usize = 10;
+const MIN_HEIGHT: usize = 3;
+
+/// Most labels under a numeric x axis.
+const X_LABELS: usize = 10;
+
+/// Markers of the series of line and scatter plots, in order.
+const MARKERS: [char; 8] = ['*', '+', 'o', 'x', '#', '@', '%', '&'];
+
+/// ANSI foreground color of each series, in order.
+const COLORS: [u8; 8] = [31, 32, 33, 34, 35, 36, 91, 94];
+
+/// Glyph standing for a series in the legend of a Braille chart.
+const BRAILLE_FULL: char = '⣿';
+
+/// Cell of a bar or histogram bin.
+const BAR: char = '#';
+
+/// Cell between the points of a line.
+const SEGMENT: char = '.';
+
+/// First Braille pattern, with no dot raised.
+const BRAILLE_BLANK: u32 = 0x2800;
+
+/// Bit of the Braille dot at column `x` (0 or 1) and row `y` (0 to 3 from
+/// the top) of a character.
+const BRAILLE_DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+/// Size and resolution of a text chart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChartOptions {
+    /// Plot width in characters, without the y axis labels.
+    pub width: usize,
+    /// Plot height in lines, without the title and the x axis.
+    pub height: usize,
+    /// Draw with Braille characters of 2x4 dots instead of one marker per
+    /// character.
+    pub braille: bool,
+    /// Color each series with ANSI escape codes.
+    pub color: bool,
+}
+
+/// How chart commands draw.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ChartFormat {
+    /// Characters for the terminal
+    #[default]
+    Text,
+    /// A standalone svg image
+    Svg,
+    /// A png image
+    Png,
+}
+
+impl Default for ChartOptions {
+    fn default() -> Self {
+        Self {
+            width: GRAPH_WIDTH,
+            height: GRAPH_HEIGHT,
+            braille: false,
+            color: false,
+        }
+    }
+}
+
+/// Range of the values along one axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Axis {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Axis {
+    fn new(values: impl IntoIterator<Item = f64>) -> Result<Self, String> {
+        let (min, max) = values
+            .into_iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+                (min.min(value), max.max(value))
+            });
+        if min > max {
+            return Err("No values to plot".to_string());
+        }
+        Ok(Self { min, max })
+    }
+
+    /// Stretch the range to include zero, where bars start.
+    fn with_zero(self) -> Self {
+        Self {
+            min: self.min.min(0.0),
+            max: self.max.max(0.0),
+        }
+    }
+
+    /// Cell holding `value` when the axis is `cells` cells long.
+    fn cell(&self, value: f64, cells: usize) -> usize {
+        if self.min == self.max {
+            cells / 2
+        } else {
+            ((value - self.min) / (self.max - self.min) * (cells - 1) as f64).round() as usize
+        }
+    }
+
+    /// Position of `value` along the axis, from 0 at `min` to 1 at `max`.
+    pub fn fraction(&self, value: f64) -> f64 {
+        if self.min == self.max {
+            0.5
+        } else {
+            (value - self.min) / (self.max - self.min)
+        }
+    }
+
+    /// Round values inside the range, about `count` of them, spaced by 1, 2
+    /// or 5 times a power of ten.
+    pub fn ticks(&self, count: usize) -> Vec<f64> {
+        let span = self.max - self.min;
+        if span <= 0.0 || !span.is_finite() {
+            return vec![self.min];
+        }
+        let rough = span / count.max(1) as f64;
+        let magnitude = 10f64.powf(rough.log10().floor());
+        let step = [1.0, 2.0, 5.0, 10.0]
+            .into_iter()
+            .map(|multiple| multiple * magnitude)
+            .find(|&step| step >= rough)
+            .unwrap_or(10.0 * magnitude);
+        let (first, last) = (
+            (self.min / step).ceil() as i64,
+            (self.max / step).floor() as i64,
+        );
+        (first..=last).map(|i| i as f64 * step).collect()
+    }
+
+    /// Value shown at cell `i` of an axis `cells` cells long.
+    fn value_at(&self, i: usize, cells: usize) -> f64 {
+        if self.min == self.max {
+            self.min
+        } else {
+            self.min + (self.max - self.min) * (i as f64 / (cells - 1) as f64)
+        }
+    }
+}
+
+/// Dots of a chart, drawn with the shared axes and labels.
+///
+/// A dot is a whole character, or one of the 2x4 dots of a Braille
+/// character in Braille mode.
+struct Grid {
+    options: ChartOptions,
+    /// Marker and series of each dot, rows from the top of the chart down.
+    dots: Vec<Vec<Option<(char, usize)>>>,
+}
+
+impl Grid {
+    fn new(options: &ChartOptions) -> Result<Self, String> {
+        if options.width < MIN_WIDTH || options.height < MIN_HEIGHT {
+            return Err(format!(
+                "Chart size {}x{} is too small, it needs at least {MIN_WIDTH}x{MIN_HEIGHT}",
+                options.width, options.height
+            ));
+        }
+        let (dots_x, dots_y) = Self::resolution(options);
+        Ok(Self {
+            options: *options,
+            dots: vec![vec![None; options.width * dots_x]; options.height * dots_y],
+        })
+    }
+
+    /// Dots per character across and down.
+    fn resolution(options: &ChartOptions) -> (usize, usize) {
+        if options.braille { (2, 4) } else { (1, 1) }
+    }
+
+    fn width(&self) -> usize {
+        self.dots[0].len()
+    }
+
+    fn height(&self) -> usize {
+        self.dots.len()
+    }
+
+    /// Dot column of `x` along `x_axis` and dot row of `y` along `y_axis`.
+    fn position(&self, x_axis: Axis, x: f64, y_axis: Axis, y: f64) -> (usize, usize) {
+        (x_axis.cell(x, self.width()), y_axis.cell(y, self.height()))
+    }
+
+    /// Put the `marker` of `series` at dot column `x`, on dot row `y`
+    /// counted from the bottom.
+    fn plot(&mut self, x: usize, y: usize, marker: char, series: usize) {
+        let (width, height) = (self.width(), self.height());
+        if x < width && y < height {
+            self.dots[height - 1 - y][x] = Some((marker, series));
+        }
+    }
+
+    /// Mark the dots on the straight line between `from` and `to`, with
+    /// Bresenham's algorithm.
+    fn segment(&mut self, from: (usize, usize), to: (usize, usize), marker: char, series: usize) {
+        let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+        let (x1, y1) = (to.0 as i64, to.1 as i64);
+        let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+        let (step_x, step_y) = ((x1 - x).signum(), (y1 - y).signum());
+        let mut error = dx + dy;
+        loop {
+            self.plot(x as usize, y as usize, marker, series);
+            if (x, y) == (x1, y1) {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Fill the characters `columns` from the zero line of `y_axis` up (or
+    /// down) to `value`. Empty bars are left out.
+    fn bar(&mut self, columns: Range<usize>, y_axis: Axis, value: f64) {
+        let (dots_x, _) = Self::resolution(&self.options);
+        self.dot_bar(columns.start * dots_x..columns.end * dots_x, y_axis, value);
+    }
+
+    /// Like [`Grid::bar`], over the dot columns `dots`.
+    fn dot_bar(&mut self, dots: Range<usize>, y_axis: Axis, value: f64) {
+        if value == 0.0 {
+            return;
+        }
+        let zero = y_axis.cell(0.0, self.height());
+        let top = y_axis.cell(value, self.height());
+        for y in zero.min(top)..=zero.max(top) {
+            for x in dots.clone() {
+                self.plot(x, y, BAR, 0);
+            }
+        }
+    }
+
+    /// Character of the dots at character column `x` and line `y`, with the
+    /// series drawn there. In Braille mode that is the last series found
+    /// among the dots of the character.
+    fn character(&self, x: usize, y: usize) -> Option<(char, usize)> {
+        if !self.options.braille {
+            return self.dots[y][x];
+        }
+        let mut pattern = 0;
+        let mut series = None;
+        for (dx, bits) in BRAILLE_DOTS.iter().enumerate() {
+            for (dy, bit) in bits.iter().enumerate() {
+                if let Some((_, drawn)) = self.dots[y * 4 + dy][x * 2 + dx] {
+                    pattern |= bit;
+                    series = Some(drawn);
+                }
+            }
+        }
+        let glyph = char::from_u32(BRAILLE_BLANK + pattern)?;
+        series.map(|series| (glyph, series))
+    }
+
+    /// `glyph` in the color of `series` when coloring is on.
+    fn paint(&self, glyph: char, series: usize) -> String {
+        if self.options.color {
+            format!("\x1b[{}m{glyph}\x1b[0m", COLORS[series % COLORS.len()])
+        } else {
+            glyph.to_string()
+        }
+    }
+
+    /// Legend naming each series after the glyph it is drawn with.
+    fn legend<S: AsRef<str>>(&self, names: &[S]) -> String {
+        let entries: Vec<String> = names
+            .iter()
+            .zip(MARKERS)
+            .enumerate()
+            .map(|(series, (name, marker))| {
+                let glyph = if self.options.braille {
+                    BRAILLE_FULL
+                } else {
+                    marker
+                };
+                format!("{} {}", self.paint(glyph, series), name.as_ref())
+            })
+            .collect();
+        format!("        {}", entries.join("  "))
+    }
+
+    /// Lay out `title`, the lines labelled with `y_axis` values on the left
+    /// and `secondary` values on the right, the x axis and the `x_labels`
+    /// line under it.
+    fn render(
+        self,
+        title: String,
+        y_axis: Axis,
+        secondary: Option<Axis>,
+        x_labels: String,
+    ) -> String {
+        let ChartOptions { width, height, .. } = self.options;
+        let mut graph = vec![title];
+        for y in 0..height {
+            let y_val = y_axis.value_at(height - 1 - y, height);
+            let row: String = (0..width)
+                .map(|x| match self.character(x, y) {
+                    Some((glyph, series)) => self.paint(glyph, series),
+                    None => " ".to_string(),
+                })
+                .collect();
+            let line = match secondary {
+                Some(axis) => {
+                    let y2_val = axis.value_at(height - 1 - y, height);
+                    format!("{y_val:>6.1} |{row}| {y2_val:.1}")
+                }
+                None => format!("{y_val:>6.1} |{row}"),
+            };
+            graph.push(line);
+        }
+        graph.push(format!("        {}", "-".repeat(width + 1)));
+        graph.push(x_labels);
+        graph.join("\n")
+    }
+}
+
+/// Points of column `y` against column `x`, skipping rows where either is
+/// null.
+fn points(dataset: &Dataset, x: usize, y: usize) -> Vec<(f64, f64)> {
+    dataset
+        .rows
+        .iter()
+        .filter_map(|row| Some((row[x].as_f64()?, row[y].as_f64()?)))
+        .collect()
+}
+
+/// Indices of the numeric columns `names`, one per series.
+fn series_columns(dataset: &Dataset, names: &[&str]) -> Result<Vec<usize>, String> {
+    if names.is_empty() {
+        return Err("No y columns passed".to_string());
+    }
+    if names.len() > MARKERS.len() {
+        return Err(format!(
+            "Too many series to plot: {}, at most {} fit",
+            names.len(),
+            MARKERS.len()
+        ));
+    }
+    names
+        .iter()
+        .map(|name| dataset.numeric_column(name))
+        .collect()
+}
+
+/// Evenly spaced values of `x_axis` under a plot `width` characters wide.
+fn numeric_labels(x_axis: Axis, width: usize) -> String {
+    let labels = (width / 10 + 1).clamp(2, X_LABELS);
+    let mut x_label_line = String::from("       ");
+
+    for i in 0..labels {
+        let pos = (width - 1) * i / (labels - 1);
+        let x_val = x_axis.value_at(pos, width);
+
+        if i > 0 {
+            let spaces_needed = pos.saturating_sub(x_label_line.len() - 7);
+            x_label_line.push_str(&" ".repeat(spaces_needed));
+        }
+        x_label_line.push_str(&format!("{x_val:>6.1}"));
+    }
+
+    x_label_line
+}
+
+/// `labels` under slots `slot` cells wide, cut to fit their slot.
+fn category_labels(labels: &[String], slot: usize) -> String {
+    let mut line = String::from("        ");
+    for label in labels {
+        let label: String = label.chars().take(slot.saturating_sub(1).max(1)).collect();
+        line.push_str(&format!("{label:<slot$}"));
+    }
+    line.trim_end().to_string()
+}
+
+/// Width of the slot of each of `count` categories across `width` characters.
+fn slot_width(count: usize, width: usize) -> Result<usize, String> {
+    if count > width {
+        return Err(format!(
+            "Too many bars to plot: {count}, at most {width} fit"
+        ));
+    }
+    Ok(width / count.max(1))
+}
+
+/// `bins` merged in runs of neighbours into at most `count` bins.
+fn merge_bins(bins: &[Bin], count: usize) -> Vec<Bin> {
+    bins.chunks(bins.len().div_ceil(count.max(1)))
+        .map(|run| Bin {
+            start: run[0].start,
+            end: run[run.len() - 1].end,
+            count: run.iter().map(|bin| bin.count).sum(),
+        })
+        .collect()
+}
+
+/// Named points of one column of a line or scatter plot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    pub name: String,
+    pub points: Vec<(f64, f64)>,
+    /// Scaled to the secondary y axis instead of the primary one.
+    pub secondary: bool,
+}
+
+/// What a chart draws.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Plot {
+    /// Points of each series, joined in order by line segments when `joined`.
+    Points {
+        series: Vec<Series>,
+        joined: bool,
+        x_axis: Axis,
+    },
+    /// One bar per category label, left out when its value is null.
+    Bars(Vec<(String, Option<f64>)>),
+    /// Bins of equal width spanning `x_axis`.
+    Histogram { bins: Vec<Bin>, x_axis: Axis },
+}
+
+/// A chart computed from a dataset, drawn as text by [`Chart::to_text`] or
+/// as an image by [`crate::svg::render`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chart {
+    pub plot: Plot,
+    /// Name of the x column.
+    pub x_label: String,
+    /// Names of the columns on the y axis.
+    pub y_label: String,
+    /// Names of the columns on the secondary y axis, if any.
+    pub secondary_label: Option<String>,
+    pub y_axis: Axis,
+    pub secondary_axis: Option<Axis>,
+}
+
+impl Chart {
+    /// The columns `ys`, and `secondary` on an axis of their own, against
+    /// column `x`, joining the points in order of `x`.
+    pub fn line<S: AsRef<str>>(
+        dataset: &Dataset,
+        x: &str,
+        ys: &[S],
+        secondary: &[S],
+    ) -> Result<Self, String> {
+        let names: Vec<&str> = ys.iter().chain(secondary).map(AsRef::as_ref).collect();
+        if dataset.column_index(x).is_none()
+            || names.iter().any(|y| dataset.column_index(y).is_none())
+        {
+            return Err("Invalid x or y argument".to_string());
+        }
+
+        let x = dataset.numeric_column(x)?;
+        let columns = series_columns(dataset, &names)?;
+        let series = columns
+            .iter()
+            .enumerate()
+            .map(|(i, &y)| {
+                let mut points = points(dataset, x, y);
+                points.sort_by(|a, b| a.0.total_cmp(&b.0));
+                Series {
+                    name: dataset.headers[y].clone(),
+                    points,
+                    secondary: i >= ys.len(),
+                }
+            })
+            .collect();
+        Self::points(dataset.headers[x].clone(), series, true)
+    }
+
+    /// The columns `ys` against column `x` as unconnected points.
+    pub fn scatter<S: AsRef<str>>(dataset: &Dataset, x: &str, ys: &[S]) -> Result<Self, String> {
+        let x = dataset.numeric_column(x)?;
+        let names: Vec<&str> = ys.iter().map(AsRef::as_ref).collect();
+        let series = series_columns(dataset, &names)?
+            .into_iter()
+            .map(|y| Series {
+                name: dataset.headers[y].clone(),
+                points: points(dataset, x, y),
+                secondary: false,
+            })
+            .collect();
+        Self::points(dataset.headers[x].clone(), series, false)
+    }
+
+    /// Line or scatter chart of `series`, each y axis fitted to its series.
+    fn points(x_label: String, series: Vec<Series>, joined: bool) -> Result<Self, String> {
+        let on_axis = |secondary: bool| {
+            series
+                .iter()
+                .filter(move |series| series.secondary == secondary)
+        };
+        let axis = |secondary: bool| {
+            Axis::new(on_axis(secondary).flat_map(|series| series.points.iter().map(|&(_, y)| y)))
+        };
+        let label = |secondary: bool| {
+            let names: Vec<&str> = on_axis(secondary)
+                .map(|series| series.name.as_str())
+                .collect();
+            names.join(", ")
+        };
+
+        let y_axis = axis(false)?;
+        let x_axis = Axis::new(
+            series
+                .iter()
+                .flat_map(|series| series.points.iter().map(|&(x, _)| x)),
+        )?;
+        let has_secondary = series.iter().any(|series| series.secondary);
+        let secondary_axis = if has_secondary {
+            Some(axis(true)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            x_label,
+            y_label: label(false),
+            secondary_label: has_secondary.then(|| label(true)),
+            y_axis,
+            secondary_axis,
+            plot: Plot::Points {
+                series,
+                joined,
+                x_axis,
+            },
+        })
+    }
+
+    /// One bar per distinct value of column `x`, in order of first
+    /// appearance, as tall as `aggregate` over its rows.
+    pub fn bar(dataset: &Dataset, x: &str, aggregate: &GroupAggregate) -> Result<Self, String> {
+        let groups = dataset.group_by(&[x], slice::from_ref(aggregate), false)?;
+        let bars: Vec<(String, Option<f64>)> = groups
+            .rows
+            .iter()
+            .map(|row| (row[0].to_string(), row[1].as_f64()))
+            .collect();
+        let y_axis = Axis::new(bars.iter().filter_map(|(_, value)| *value))?.with_zero();
+        let [x_label, y_label] = [0, 1].map(|idx| groups.headers[idx].clone());
+        Ok(Self {
+            plot: Plot::Bars(bars),
+            x_label,
+            y_label,
+            secondary_label: None,
+            y_axis,
+            secondary_axis: None,
+        })
+    }
+
+    /// Counts of the values of column `column` in `bins` bins of equal
+    /// width, or as many as `rule` picks. At most [`MAX_BINS`] can be asked
+    /// for.
+    pub fn histogram(
+        dataset: &Dataset,
+        column: &str,
+        bins: Option<usize>,
+        rule: BinRule,
+    ) -> Result<Self, String> {
+        if let Some(bins) = bins.filter(|&bins| bins > MAX_BINS) {
+            return Err(format!("At most {MAX_BINS} bins can be drawn, not {bins}"));
+        }
+        let idx = dataset.numeric_column(column)?;
+        let mut values: Vec<f64> = dataset
+            .rows
+            .iter()
+            .filter_map(|row| row[idx].as_f64())
+            .collect();
+        values.sort_by(f64::total_cmp);
+
+        let bins = stats::histogram(&values, bins.unwrap_or_else(|| rule.bins(&values)));
+        let (Some(first), Some(last)) = (bins.first(), bins.last()) else {
+            return Err("No values to plot".to_string());
+        };
+        let x_axis = Axis {
+            min: first.start,
+            max: last.end,
+        };
+        let y_axis = Axis::new(bins.iter().map(|bin| bin.count as f64))?.with_zero();
+        Ok(Self {
+            plot: Plot::Histogram { bins, x_axis },
+            x_label: dataset.headers[idx].clone(),
+            y_label: "count".to_string(),
+            secondary_label: None,
+            y_axis,
+            secondary_axis: None,
+        })
+    }
+
+    /// The y axis `series` is scaled to.
+    pub fn series_axis(&self, series: &Series) -> Axis {
+        match self.secondary_axis {
+            Some(axis) if series.secondary => axis,
+            _ => self.y_axis,
+        }
+    }
+
+    /// Names of the columns on each axis, e.g. `y-axis (price) x-axis (id)`.
+    pub fn title(&self) -> String {
+        let mut title = format!("y-axis ({})", self.y_label);
+        if let Some(label) = &self.secondary_label {
+            title.push_str(&format!(" secondary y-axis ({label})"));
+        }
+        title.push_str(&format!(" x-axis ({})", self.x_label));
+        title
+    }
+
+    /// Legend entries naming each series, none when there is only one.
+    pub fn legend(&self) -> Vec<String> {
+        match &self.plot {
+            Plot::Points { series, .. } if series.len() > 1 => series
+                .iter()
+                .map(|series| {
+                    if series.secondary {
+                        format!("{} (right)", series.name)
+                    } else {
+                        series.name.clone()
+                    }
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Draw the chart with characters, one marker per series. Histograms
+    /// with more bins than dot columns merge neighbouring bins to fit.
+    pub fn to_text(&self, options: &ChartOptions) -> Result<String, String> {
+        let (dots_x, _) = Grid::resolution(options);
+        if let Plot::Histogram { bins, x_axis } = &self.plot
+            && bins.len() > options.width * dots_x
+        {
+            let merged = merge_bins(bins, options.width * dots_x);
+            let chart = Self {
+                y_axis: Axis::new(merged.iter().map(|bin| bin.count as f64))?.with_zero(),
+                plot: Plot::Histogram {
+                    bins: merged,
+                    x_axis: *x_axis,
+                },
+                ..self.clone()
+            };
+            return chart.to_text(options);
+        }
+
+        let mut grid = Grid::new(options)?;
+        let width = options.width;
+        let x_labels = match &self.plot {
+            Plot::Points {
+                series,
+                joined,
+                x_axis,
+            } => {
+                let positions: Vec<Vec<(usize, usize)>> = series
+                    .iter()
+                    .map(|series| {
+                        let y_axis = self.series_axis(series);
+                        series
+                            .points
+                            .iter()
+                            .map(|&(x_val, y_val)| grid.position(*x_axis, x_val, y_axis, y_val))
+                            .collect()
+                    })
+                    .collect();
+                if *joined {
+                    for (i, points) in positions.iter().enumerate() {
+                        for pair in points.windows(2) {
+                            grid.segment(pair[0], pair[1], SEGMENT, i);
+                        }
+                    }
+                }
+                for (i, (points, marker)) in positions.iter().zip(MARKERS).enumerate() {
+                    for &(x_pos, y_pos) in points {
+                        grid.plot(x_pos, y_pos, marker, i);
+                    }
+                }
+                numeric_labels(*x_axis, width)
+            }
+            Plot::Bars(bars) => {
+                let slot = slot_width(bars.len(), width)?;
+                for (i, (_, value)) in bars.iter().enumerate() {
+                    if let Some(value) = value {
+                        let start = i * slot;
+                        grid.bar(start..start + (slot - 1).max(1), self.y_axis, *value);
+                    }
+                }
+                let labels: Vec<String> = bars.iter().map(|(label, _)| label.clone()).collect();
+                category_labels(&labels, slot)
+            }
+            Plot::Histogram { bins, x_axis } => {
+                let dots = grid.width();
+                for (i, bin) in bins.iter().enumerate() {
+                    let columns = i * dots / bins.len()..(i + 1) * dots / bins.len();
+                    grid.dot_bar(columns, self.y_axis, bin.count as f64);
+                }
+                numeric_labels(*x_axis, width)
+            }
+        };
+
+        let legend = self.legend();
+        let legend_line = grid.legend(&legend);
+        let graph = grid.render(self.title(), self.y_axis, self.secondary_axis, x_labels);
+        if legend.is_empty() {
+            return Ok(graph);
+        }
+        Ok(format!("{graph}\n{legend_line}"))
+    }
+}
+
+/// Plot the columns `ys` against column `x` as a text line graph, joining
+/// the points of each column in order of `x` with line segments.
+///
+/// The `ys` share the y axis on the left, while the `secondary` columns are
+/// scaled to an axis of their own labelled on the right. Each column gets
+/// its own marker and color, named in a legend when there are several.
+pub fn line_graph<S: AsRef<str>>(
+    dataset: &Dataset,
+    x: &str,
+    ys: &[S],
+    secondary: &[S],
+    options: &ChartOptions,
+) -> Result<String, String> {
+    // count,offset
+    // 0,5
+    // 1,4
+    // 2,3
+    // 3,2
+    // 4,1
+    // 5,0
+    //
+    // (x,y) = (count,offset)
+    //
+    // 5 *
+    // 4 *..*
+    // 3 *  ..*
+    // 2 *     ..*
+    // 1 *        ..*
+    // 0 *           ..*
+    //   *  *  *  *  *  *
+    //   0  1  2  3  4  5
+
+    Chart::line(dataset, x, ys, secondary)?.to_text(options)
+}
+
+/// Plot one vertical bar per distinct value of column `x` as text, see
+/// [`Chart::bar`].
+pub fn bar_chart(
+    dataset: &Dataset,
+    x: &str,
+    aggregate: &GroupAggregate,
+    options: &ChartOptions,
+) -> Result<String, String> {
+    Chart::bar(dataset, x, aggregate)?.to_text(options)
+}
+
+/// Plot how the values of column `column` spread as a text histogram, see
+/// [`Chart::histogram`].
+pub fn histogram(
+    dataset: &Dataset,
+    column: &str,
+    bins: Option<usize>,
+    rule: BinRule,
+    options: &ChartOptions,
+) -> Result<String, String> {
+    Chart::histogram(dataset, column, bins, rule)?.to_text(options)
+}
+
+/// Plot each column of `ys` against column `x` with a marker and color of
+/// its own, followed by a legend when there are several.
+pub fn scatter_chart<S: AsRef<str>>(
+    dataset: &Dataset,
+    x: &str,
+    ys: &[S],
+    options: &ChartOptions,
+) -> Result<String, String> {
+    Chart::scatter(dataset, x, ys)?.to_text(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dataset() -> Dataset {
+        let records = [
+            ["north", "1", "10", "2"],
+            ["south", "2", "20", "4"],
+            ["north", "3", "", "6"],
+            ["east", "4", "5", "8"],
+        ]
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+        .collect();
+        let headers = ["region", "id", "price", "quantity"]
+            .map(String::from)
+            .to_vec();
+        Dataset::from_records(headers, records).unwrap()
+    }
+
+    #[test]
+    fn test_axis_labels() {
+        let options = ChartOptions::default();
+        let graph = line_graph(&dataset(), "id", &["quantity"], &[], &options).unwrap();
+        let lines: Vec<&str> = graph.lines().collect();
+        assert_eq!(lines[0], "y-axis (quantity) x-axis (id)");
+        assert!(lines[1].starts_with("   8.0 |"));
+        assert!(lines[GRAPH_HEIGHT].starts_with("   2.0 |*."));
+        assert!(lines[GRAPH_HEIGHT + 2].starts_with("          1.0"));
+        assert!(lines[GRAPH_HEIGHT + 2].ends_with("4.0"));
+    }
+
+    #[test]
+    fn test_bars_and_bins() {
+        let options = ChartOptions::default();
+        let aggregate: GroupAggregate = "sum:price".parse().unwrap();
+        let graph = bar_chart(&dataset(), "region", &aggregate, &options).unwrap();
+        let lines: Vec<&str> = graph.lines().collect();
+        assert_eq!(lines[0], "y-axis (sum_price) x-axis (region)");
+        assert!(lines[1].starts_with("  20.0 |             #"));
+        assert!(lines[GRAPH_HEIGHT].starts_with("   0.0 |####"));
+        assert!(lines[GRAPH_HEIGHT + 2].starts_with("        north        south        east"));
+
+        let graph = histogram(&dataset(), "quantity", Some(2), BinRule::Sturges, &options);
+        assert!(
+            graph
+                .unwrap()
+                .lines()
+                .nth(1)
+                .unwrap()
+                .starts_with("   2.0 |####")
+        );
+        let error = histogram(&dataset(), "region", None, BinRule::Sturges, &options);
+        assert!(error.is_err());
+        let error = histogram(
+            &dataset(),
+            "quantity",
+            Some(1001),
+            BinRule::Sturges,
+            &options,
+        );
+        assert_eq!(
+            error.unwrap_err(),
+            "At most 1000 bins can be drawn, not 1001"
+        );
+    }
+
+    #[test]
+    fn test_histogram_merges_bins_to_fit() {
+        let records = (1..=400).map(|i| vec![i.to_string()]).collect();
+        let dataset = Dataset::from_records(vec!["v".to_string()], records).unwrap();
+        // The rule picks 20 bins, twice as many as there are columns.
+        assert_eq!(BinRule::Sqrt.bins(&[0.0; 400]), 20);
+        let options = ChartOptions {
+            width: 10,
+            height: 4,
+            ..ChartOptions::default()
+        };
+        let graph = histogram(&dataset, "v", None, BinRule::Sqrt, &options).unwrap();
+        let lines: Vec<&str> = graph.lines().collect();
+        // Pairs of bins of 20 values each merge into full-height bars.
+        assert!(lines[1].ends_with("|##########"));
+        assert!(lines[1].starts_with("  40.0"));
+
+        // Braille has a dot column for each bin.
+        let braille = ChartOptions {
+            braille: true,
+            ..options
+        };
+        let graph = histogram(&dataset, "v", Some(20), BinRule::Sqrt, &braille).unwrap();
+        assert!(graph.lines().nth(1).unwrap().starts_with("  20.0"));
+        assert!(histogram(&dataset, "v", Some(50), BinRule::Sqrt, &braille).is_ok());
+    }
+
+    #[test]
+    fn test_scatter_legend() {
+        let options = ChartOptions::default();
+        let graph = scatter_chart(&dataset(), "id", &["price", "quantity"], &options).unwrap();
+        assert!(graph.starts_with("y-axis (price, quantity) x-axis (id)"));
+        assert!(graph.ends_with("        * price  + quantity"));
+        assert!(graph.contains('+'));
+    }
+
+    #[test]
+    fn test_line_series_and_secondary_axis() {
+        let options = ChartOptions::default();
+        let graph = line_graph(&dataset(), "id", &["price", "id"], &["quantity"], &options);
+        let graph = graph.unwrap();
+        let lines: Vec<&str> = graph.lines().collect();
+        assert_eq!(
+            lines[0],
+            "y-axis (price, id) secondary y-axis (quantity) x-axis (id)"
+        );
+        assert!(lines[1].starts_with("  20.0 |"));
+        assert!(lines[1].ends_with("| 8.0"));
+        assert!(lines[GRAPH_HEIGHT].ends_with("| 2.0"));
+        assert_eq!(
+            lines[GRAPH_HEIGHT + 3],
+            "        * price  + id  o quantity (right)"
+        );
+
+        let colored = ChartOptions {
+            color: true,
+            ..options
+        };
+        let graph = line_graph(&dataset(), "id", &["price", "id"], &[], &colored).unwrap();
+        assert!(graph.contains("\x1b[31m*\x1b[0m price"));
+        assert!(graph.contains("\x1b[32m+\x1b[0m id"));
+    }
+
+    #[test]
+    fn test_braille_line_and_size() {
+        let options = ChartOptions {
+            width: 10,
+            height: 3,
+            braille: true,
+            color: false,
+        };
+        let graph = line_graph(&dataset(), "id", &["quantity"], &[], &options).unwrap();
+        let lines: Vec<&str> = graph.lines().collect();
+        assert_eq!(lines.len(), 3 + 3);
+        // A rising line through 20x12 dots, from the bottom left dot of the
+        // first character to the top right dot of the last one.
+        assert_eq!(lines[3], "   2.0 |⡠⠔⠉       ");
+        assert_eq!(lines[1], "   8.0 |       ⣀⠔⠊");
+        assert!(lines[1..4].iter().all(|line| line.chars().count() == 18));
+
+        let too_small = ChartOptions {
+            width: 4,
+            ..options
+        };
+        assert_eq!(
+            line_graph(&dataset(), "id", &["quantity"], &[], &too_small).unwrap_err(),
+            "Chart size 4x3 is too small, it needs at least 10x3"
+        );
+    }
+}
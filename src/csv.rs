@@ -0,0 +1,277 @@
+use std::io::{self, BufRead, Write};
+
+/// Delimiters tried when sniffing the dialect of an input.
+const SNIFF_DELIMITERS: [char; 4] = [',', '\t', ';', '|'];
+/// Number of lines sampled when sniffing.
+const SNIFF_LINES: usize = 20;
+
+/// Field delimiter and quote character of a csv file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dialect {
+    pub delimiter: char,
+    pub quote: char,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quote: '"',
+        }
+    }
+}
+
+impl Dialect {
+    /// Guess the delimiter from the first lines of `sample`.
+    ///
+    /// The winning delimiter is the one that splits every sampled line into
+    /// the same number of fields, preferring the one producing most fields.
+    /// Falls back to a comma when no candidate appears consistently.
+    pub fn sniff(sample: &str, quote: char) -> Self {
+        let lines: Vec<&str> = sample
+            .lines()
+            .map(trim_line_ending)
+            .filter(|line| !line.is_empty())
+            .take(SNIFF_LINES)
+            .collect();
+
+        let delimiter = SNIFF_DELIMITERS
+            .into_iter()
+            .filter_map(|delimiter| {
+                let counts: Vec<usize> = lines
+                    .iter()
+                    .map(|line| count_unquoted(line, delimiter, quote))
+                    .collect();
+                let first = *counts.first()?;
+                let consistent = counts.iter().all(|&count| count == first);
+                (first > 0).then_some((delimiter, consistent, first))
+            })
+            .max_by_key(|&(_, consistent, count)| (consistent, count))
+            .map_or(',', |(delimiter, _, _)| delimiter);
+
+        Self { delimiter, quote }
+    }
+
+    /// Sniff the dialect from the data buffered in `reader` without consuming it.
+    pub fn sniff_reader<R: BufRead>(reader: &mut R, quote: char) -> io::Result<Self> {
+        let buf = reader.fill_buf()?;
+        // Only look at complete lines, the buffer may end mid-record.
+        let end = buf.iter().rposition(|&b| b == b'\n').unwrap_or(buf.len());
+        Ok(Self::sniff(&String::from_utf8_lossy(&buf[..end]), quote))
+    }
+}
+
+fn count_unquoted(line: &str, delimiter: char, quote: char) -> usize {
+    let mut in_quotes = false;
+    line.chars()
+        .filter(|&c| {
+            if c == quote {
+                in_quotes = !in_quotes;
+            }
+            c == delimiter && !in_quotes
+        })
+        .count()
+}
+
+/// Streaming RFC 4180 record reader.
+///
+/// Handles quoted fields, escaped quotes (`""`), CRLF line endings and
+/// line breaks embedded inside quoted fields. Blank lines are skipped.
+pub struct CsvReader<R: BufRead> {
+    reader: R,
+    dialect: Dialect,
+    line: usize,
+}
+
+impl<R: BufRead> CsvReader<R> {
+    pub fn new(reader: R, dialect: Dialect) -> Self {
+        Self {
+            reader,
+            dialect,
+            line: 0,
+        }
+    }
+
+    /// Read the next record, returning `None` at the end of input.
+    fn read_record(&mut self) -> io::Result<Option<Vec<String>>> {
+        let mut buf = String::new();
+
+        loop {
+            buf.clear();
+            if self.reader.read_line(&mut buf)? == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            if !trim_line_ending(&buf).is_empty() {
+                break;
+            }
+        }
+
+        let start_line = self.line;
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut in_quotes = false;
+        let Dialect { delimiter, quote } = self.dialect;
+
+        loop {
+            let mut chars = buf.chars().peekable();
+            while let Some(c) = chars.next() {
+                if in_quotes {
+                    match c {
+                        c if c == quote && chars.peek() == Some(&quote) => {
+                            field.push(quote);
+                            chars.next();
+                        }
+                        c if c == quote => in_quotes = false,
+                        _ => field.push(c),
+                    }
+                    continue;
+                }
+
+                match c {
+                    c if c == quote && field.is_empty() => in_quotes = true,
+                    c if c == delimiter => fields.push(std::mem::take(&mut field)),
+                    '\r' if chars.peek() == Some(&'\n') => {}
+                    '\n' => {}
+                    _ => field.push(c),
+                }
+            }
+
+            if !in_quotes {
+                break;
+            }
+
+            // The quoted field continues on the next physical line.
+            buf.clear();
+            if self.reader.read_line(&mut buf)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unterminated quoted field starting on line {start_line}"),
+                ));
+            }
+            self.line += 1;
+        }
+
+        fields.push(field);
+        Ok(Some(fields))
+    }
+}
+
+impl<R: BufRead> Iterator for CsvReader<R> {
+    type Item = io::Result<Vec<String>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Write one record, quoting fields that contain delimiters, quotes or line breaks.
+pub fn write_record<W, I, S>(writer: &mut W, fields: I, dialect: &Dialect) -> io::Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let Dialect { delimiter, quote } = *dialect;
+    let fields: Vec<S> = fields.into_iter().collect();
+    // A lone empty field would otherwise be written as a blank line, which readers skip.
+    if let [field] = fields.as_slice()
+        && field.as_ref().is_empty()
+    {
+        return writeln!(writer, "{quote}{quote}");
+    }
+
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            write!(writer, "{delimiter}")?;
+        }
+        let field = field.as_ref();
+        if field.contains([delimiter, quote, '\r', '\n']) {
+            let escaped = field.replace(quote, &format!("{quote}{quote}"));
+            write!(writer, "{quote}{escaped}{quote}")?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\n")
+}
+
+fn trim_line_ending(line: &str) -> &str {
+    line.trim_end_matches(['\r', '\n'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Vec<Vec<String>> {
+        CsvReader::new(input.as_bytes(), Dialect::default())
+            .collect::<io::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_quoted_fields() {
+        let records = parse("name,price\n\"Smith, John\",12\n\"say \"\"hi\"\"\",3\n");
+        assert_eq!(records[1], vec!["Smith, John", "12"]);
+        assert_eq!(records[2], vec!["say \"hi\"", "3"]);
+    }
+
+    #[test]
+    fn test_crlf_and_multiline_fields() {
+        let records = parse("a,b\r\n\"line1\r\nline2\",2\r\n\r\n3,4\r\n");
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1], vec!["line1\r\nline2", "2"]);
+        assert_eq!(records[2], vec!["3", "4"]);
+    }
+
+    #[test]
+    fn test_empty_fields_and_unterminated_quote() {
+        assert_eq!(parse("a,,\n"), vec![vec!["a", "", ""]]);
+
+        let result: io::Result<Vec<_>> =
+            CsvReader::new("a\n\"open\n".as_bytes(), Dialect::default()).collect();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_write_record_round_trip() {
+        let record = vec!["plain", "a,b", "say \"hi\"", "two\nlines"];
+        let mut buf = Vec::new();
+        write_record(&mut buf, &record, &Dialect::default()).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buf.clone()).unwrap(),
+            "plain,\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\"\n"
+        );
+        assert_eq!(parse(std::str::from_utf8(&buf).unwrap()), vec![record]);
+
+        let mut buf = Vec::new();
+        write_record(&mut buf, [""], &Dialect::default()).unwrap();
+        assert_eq!(parse(std::str::from_utf8(&buf).unwrap()), vec![vec![""]]);
+    }
+
+    #[test]
+    fn test_sniff_delimiter() {
+        assert_eq!(Dialect::sniff("a\tb\tc\n1\t2\t3\n", '"').delimiter, '\t');
+        assert_eq!(Dialect::sniff("a;b\n\"1,5\";2\n", '"').delimiter, ';');
+        assert_eq!(Dialect::sniff("a|b|c\n1|2|3\n", '"').delimiter, '|');
+        assert_eq!(Dialect::sniff("single\ncolumn\n", '"').delimiter, ',');
+    }
+
+    #[test]
+    fn test_custom_dialect() {
+        let dialect = Dialect {
+            delimiter: ';',
+            quote: '\'',
+        };
+        let records: Vec<Vec<String>> = CsvReader::new("a;b\n'x;y';'it''s'\n".as_bytes(), dialect)
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(records[1], vec!["x;y", "it's"]);
+
+        let mut buf = Vec::new();
+        write_record(&mut buf, &records[1], &dialect).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "'x;y';'it''s'\n");
+    }
+}
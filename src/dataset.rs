@@ -0,0 +1,647 @@
+use std::{io::BufRead, iter::zip};
+
+use crate::{
+    csv::Dialect,
+    expr::Expr,
+    extsort::{ExternalSorter, SORT_CHUNK_ROWS},
+    filter::{Operator, RowFilter},
+    formula,
+    group::{self, GroupAggregate},
+    join::{self, JoinKind},
+    output::{self, NumberFormat},
+    select::{self, ColumnPattern, Selection},
+    sketch::QuantileSketch,
+    sort::{RowOrder, SortKey, SortOptions},
+    stats::{self, Aggregate, QuantileMethod},
+    stream,
+    value::{ColumnType, Value},
+};
+
+/// Column-typed, in-memory table.
+///
+/// ```
+/// use mud::{Dataset, csv::Dialect, filter::Operator};
+///
+/// let input = "id,price\n1,20\n2,15\n3,16.5\n";
+/// let mut dataset = Dataset::from_reader(input.as_bytes(), Dialect::default(), false).unwrap();
+/// dataset.filter("price", Operator::Gt, Some("15")).unwrap();
+/// dataset.sort_by("price", false).unwrap();
+/// assert_eq!(dataset.rows.len(), 2);
+/// assert_eq!(dataset.mean(&["price"]).unwrap(), vec![mud::value::Value::Float(18.25)]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    pub headers: Vec<String>,
+    pub types: Vec<ColumnType>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Dataset {
+    /// Build a table from raw records, inferring each column's type from its cells.
+    pub fn from_records(headers: Vec<String>, records: Vec<Vec<String>>) -> Result<Self, String> {
+        if let Some(idx) = records
+            .iter()
+            .position(|record| record.len() != headers.len())
+        {
+            return Err(format!(
+                "Mismatch between header count and data columns on record {}",
+                idx + 1
+            ));
+        }
+
+        let types: Vec<ColumnType> = (0..headers.len())
+            .map(|idx| ColumnType::infer(records.iter().map(|record| record[idx].as_str())))
+            .collect();
+
+        let rows = records
+            .into_iter()
+            .map(|record| {
+                record
+                    .iter()
+                    .zip(&types)
+                    .map(|(cell, ty)| ty.parse(cell))
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self {
+            headers,
+            types,
+            rows,
+        })
+    }
+
+    /// Collect a row stream into memory.
+    pub fn from_stream<I>(stream: RowStream<I>) -> Result<Self, String>
+    where
+        I: Iterator<Item = Result<Vec<Value>, String>>,
+    {
+        Ok(Self {
+            headers: stream.headers,
+            types: stream.types,
+            rows: stream.rows.collect::<Result<_, String>>()?,
+        })
+    }
+
+    /// Read a csv input, inferring column types from every record.
+    pub fn from_reader<R: BufRead>(
+        reader: R,
+        dialect: Dialect,
+        no_header: bool,
+    ) -> Result<Self, String> {
+        let (headers, records) = stream::open(reader, dialect, no_header)?;
+        let records = records.collect::<Result<_, String>>()?;
+        Self::from_records(headers, records)
+    }
+
+    /// Stable sort of the rows by one column, nulls last.
+    pub fn sort_by(&mut self, column: &str, reverse: bool) -> Result<(), String> {
+        self.sort(&[SortKey::new(column, reverse)], SortOptions::default())
+    }
+
+    /// Stable sort of the rows by several keys, see [`RowOrder`].
+    pub fn sort(&mut self, keys: &[SortKey], options: SortOptions) -> Result<(), String> {
+        let order = RowOrder::new(self, keys, options)?;
+        self.rows.sort_by(|a, b| order.compare(a, b));
+        Ok(())
+    }
+
+    /// Keep the rows matching `COLUMN OPERATOR VALUE`.
+    pub fn filter(
+        &mut self,
+        column: &str,
+        operator: Operator,
+        argument: Option<&str>,
+    ) -> Result<(), String> {
+        let filter = RowFilter::new(self, column, operator, argument, false)?;
+        self.rows.retain(|row| filter.matches(row));
+        Ok(())
+    }
+
+    /// Keep the rows matching a boolean expression, see [`Expr`].
+    pub fn filter_expr(&mut self, source: &str) -> Result<(), String> {
+        let expr = Expr::parse(source, self)?;
+        self.rows.retain(|row| expr.matches(row));
+        Ok(())
+    }
+
+    /// One value of `aggregate` per column in `columns`.
+    pub fn aggregate<S: AsRef<str>>(
+        &self,
+        aggregate: Aggregate,
+        columns: &[S],
+        include_nulls: bool,
+    ) -> Result<Vec<Value>, String> {
+        let indices = numeric_columns(self, columns)?;
+        stats::aggregate(self.rows.iter().map(Ok), &indices, aggregate, include_nulls)
+    }
+
+    /// Mean of each column, skipping nulls.
+    pub fn mean<S: AsRef<str>>(&self, columns: &[S]) -> Result<Vec<Value>, String> {
+        self.aggregate(Aggregate::Mean, columns, false)
+    }
+
+    /// Median of each column, skipping nulls.
+    pub fn median<S: AsRef<str>>(&self, columns: &[S]) -> Result<Vec<Value>, String> {
+        let indices = numeric_columns(self, columns)?;
+        stats::median(
+            self.rows.iter().map(Ok),
+            &indices,
+            QuantileMethod::Linear,
+            false,
+        )
+    }
+
+    /// One row per quantile of `qs` with its value in each column, skipping nulls.
+    pub fn quantiles<S: AsRef<str>>(
+        &self,
+        columns: &[S],
+        qs: &[f64],
+        method: QuantileMethod,
+    ) -> Result<Dataset, String> {
+        let indices = numeric_columns(self, columns)?;
+        let values = stats::quantiles(self.rows.iter().map(Ok), &indices, qs, method, false)?;
+        Ok(Dataset::from_quantiles(
+            column_names(self, &indices),
+            qs,
+            values,
+        ))
+    }
+
+    /// A quantile sketch of each column, see [`stats::sketch`].
+    pub fn sketch<S: AsRef<str>>(&self, columns: &[S]) -> Result<Vec<QuantileSketch>, String> {
+        let indices = numeric_columns(self, columns)?;
+        stats::sketch(self.rows.iter().map(Ok), &indices)
+    }
+
+    /// Table with a `quantile` column followed by the value of each quantile
+    /// of `qs` in the columns `names`, as computed by [`stats::quantiles`].
+    pub fn from_quantiles(names: Vec<String>, qs: &[f64], values: Vec<Vec<Value>>) -> Self {
+        let labels = qs.iter().map(|&q| Value::Float(q));
+        labelled_table(names, ("quantile", ColumnType::Float), labels, values)
+    }
+
+    /// Summary statistics of each column, see [`stats::describe`].
+    pub fn describe<S: AsRef<str>>(&self, columns: &[S]) -> Result<Dataset, String> {
+        let indices = numeric_columns(self, columns)?;
+        let stats = stats::describe(self.rows.iter().map(Ok), &indices)?;
+        Ok(describe_table(column_names(self, &indices), stats))
+    }
+
+    /// Rows joined with the `right` table on the `on` columns, see [`join::join`].
+    pub fn join<S: AsRef<str>>(
+        &self,
+        right: Dataset,
+        on: &[S],
+        how: JoinKind,
+        suffixes: (&str, &str),
+    ) -> Result<Dataset, String> {
+        let joined = join::join(self.clone().into_stream(), right, on, how, suffixes)?;
+        Dataset::from_stream(joined)
+    }
+
+    /// One row per distinct value of the `keys` columns, see [`group::group_by`].
+    pub fn group_by<S: AsRef<str>>(
+        &self,
+        keys: &[S],
+        aggregates: &[GroupAggregate],
+        include_nulls: bool,
+    ) -> Result<Dataset, String> {
+        group::group_by(
+            self,
+            self.rows.iter().map(Ok),
+            keys,
+            aggregates,
+            include_nulls,
+        )
+    }
+
+    /// Append or replace the columns computed by `formulas`, see [`formula::compile`].
+    ///
+    /// The dataset is left untouched when a formula fails to compile.
+    pub fn mutate<S: AsRef<str>>(&mut self, formulas: &[S]) -> Result<(), String> {
+        let (mut headers, mut types) = (self.headers.clone(), self.types.clone());
+        let formulas = formula::compile(&mut headers, &mut types, formulas)?;
+        (self.headers, self.types) = (headers, types);
+        let width = self.headers.len();
+        for row in &mut self.rows {
+            row.resize(width, Value::Null);
+            for (idx, formula) in &formulas {
+                row[*idx] = formula.evaluate(row);
+            }
+        }
+        Ok(())
+    }
+
+    /// Only the selected columns, in selection order, see [`select::resolve`].
+    pub fn select(
+        &self,
+        selections: &[Selection],
+        exclude: &[ColumnPattern],
+    ) -> Result<Dataset, String> {
+        Dataset::from_stream(self.clone().into_stream().select(selections, exclude)?)
+    }
+
+    /// Rows as a pretty-printed json array of objects.
+    pub fn to_json(&self) -> Result<String, String> {
+        let mut buf = Vec::new();
+        output::write_json(
+            &mut buf,
+            &self.headers,
+            self.rows.iter().map(Ok),
+            &NumberFormat::default(),
+        )?;
+        String::from_utf8(buf).map_err(|err| format!("Serialize json: {err}"))
+    }
+
+    /// Stream the rows of this table.
+    pub fn into_stream(self) -> RowStream<impl Iterator<Item = Result<Vec<Value>, String>>> {
+        RowStream {
+            headers: self.headers,
+            types: self.types,
+            rows: self.rows.into_iter().map(Ok),
+        }
+    }
+}
+
+/// Typed rows produced one at a time, e.g. while reading a large input.
+pub struct RowStream<I> {
+    pub headers: Vec<String>,
+    pub types: Vec<ColumnType>,
+    pub rows: I,
+}
+
+impl<I> RowStream<I>
+where
+    I: Iterator<Item = Result<Vec<Value>, String>>,
+{
+    /// Stable sort by one column, nulls last.
+    pub fn sort_by(
+        self,
+        column: &str,
+        reverse: bool,
+    ) -> Result<RowStream<impl Iterator<Item = Result<Vec<Value>, String>> + use<I>>, String> {
+        self.sort(&[SortKey::new(column, reverse)], SortOptions::default())
+    }
+
+    /// Stable sort by several keys. Rows are sorted in memory when they fit in
+    /// one chunk and spilled to temporary files for an external merge sort
+    /// otherwise.
+    pub fn sort(
+        self,
+        keys: &[SortKey],
+        options: SortOptions,
+    ) -> Result<RowStream<impl Iterator<Item = Result<Vec<Value>, String>> + use<I>>, String> {
+        let order = RowOrder::new(&self, keys, options)?;
+        let mut sorter = ExternalSorter::new(SORT_CHUNK_ROWS, move |a: &[Value], b: &[Value]| {
+            order.compare(a, b)
+        });
+        for row in self.rows {
+            sorter.push(row?)?;
+        }
+
+        Ok(RowStream {
+            headers: self.headers,
+            types: self.types,
+            rows: sorter.finish()?,
+        })
+    }
+
+    /// Rows matching `filter`, checked one at a time.
+    pub fn filter(
+        self,
+        filter: impl Into<Expr>,
+    ) -> RowStream<impl Iterator<Item = Result<Vec<Value>, String>>> {
+        let filter = filter.into();
+        RowStream {
+            headers: self.headers,
+            types: self.types,
+            rows: self
+                .rows
+                .filter(move |row| row.as_ref().map_or(true, |row| filter.matches(row))),
+        }
+    }
+
+    /// Only the selected columns, in selection order, see [`select::resolve`].
+    pub fn select(
+        self,
+        selections: &[Selection],
+        exclude: &[ColumnPattern],
+    ) -> Result<RowStream<impl Iterator<Item = Result<Vec<Value>, String>> + use<I>>, String> {
+        let (indices, headers): (Vec<usize>, Vec<String>) =
+            select::resolve(&self, selections, exclude)?
+                .into_iter()
+                .unzip();
+        let types = indices.iter().map(|&idx| self.types[idx]).collect();
+        Ok(RowStream {
+            headers,
+            types,
+            rows: self.rows.map(move |row| {
+                let row = row?;
+                Ok(indices.iter().map(|&idx| row[idx].clone()).collect())
+            }),
+        })
+    }
+
+    /// Rows with the columns computed by `formulas`, see [`formula::compile`].
+    pub fn mutate<S: AsRef<str>>(
+        self,
+        formulas: &[S],
+    ) -> Result<RowStream<impl Iterator<Item = Result<Vec<Value>, String>> + use<I, S>>, String>
+    {
+        let (mut headers, mut types) = (self.headers, self.types);
+        let formulas = formula::compile(&mut headers, &mut types, formulas)?;
+        let width = headers.len();
+        Ok(RowStream {
+            headers,
+            types,
+            rows: self.rows.map(move |row| {
+                let mut row = row?;
+                row.resize(width, Value::Null);
+                for (idx, formula) in &formulas {
+                    row[*idx] = formula.evaluate(&row);
+                }
+                Ok(row)
+            }),
+        })
+    }
+
+    /// The first `count` rows.
+    pub fn take(self, count: usize) -> RowStream<impl Iterator<Item = Result<Vec<Value>, String>>> {
+        RowStream {
+            headers: self.headers,
+            types: self.types,
+            rows: self.rows.take(count),
+        }
+    }
+
+    /// Erase the iterator type, e.g. to chain a varying number of stages.
+    pub fn boxed<'a>(self) -> RowStream<Box<dyn Iterator<Item = Result<Vec<Value>, String>> + 'a>>
+    where
+        I: 'a,
+    {
+        RowStream {
+            headers: self.headers,
+            types: self.types,
+            rows: Box::new(self.rows),
+        }
+    }
+
+    /// One value of `aggregate` per column, computed in a single pass.
+    pub fn aggregate<S: AsRef<str>>(
+        self,
+        aggregate: Aggregate,
+        columns: &[S],
+        include_nulls: bool,
+    ) -> Result<Vec<Value>, String> {
+        let indices = numeric_columns(&self, columns)?;
+        stats::aggregate(self.rows, &indices, aggregate, include_nulls)
+    }
+
+    /// One row per distinct value of the `keys` columns, see [`group::group_by`].
+    pub fn group_by<S: AsRef<str>>(
+        self,
+        keys: &[S],
+        aggregates: &[GroupAggregate],
+        include_nulls: bool,
+    ) -> Result<Dataset, String> {
+        // Column lookups only, the rows are consumed separately.
+        let columns = RowStream {
+            headers: self.headers,
+            types: self.types,
+            rows: (),
+        };
+        group::group_by(&columns, self.rows, keys, aggregates, include_nulls)
+    }
+
+    /// Median of each column, see [`stats::median`].
+    pub fn median<S: AsRef<str>>(
+        self,
+        columns: &[S],
+        method: QuantileMethod,
+        include_nulls: bool,
+    ) -> Result<Vec<Value>, String> {
+        let indices = numeric_columns(&self, columns)?;
+        stats::median(self.rows, &indices, method, include_nulls)
+    }
+
+    /// One row per quantile of `qs` with its value in each column, see
+    /// [`stats::quantiles`].
+    pub fn quantiles<S: AsRef<str>>(
+        self,
+        columns: &[S],
+        qs: &[f64],
+        method: QuantileMethod,
+        include_nulls: bool,
+    ) -> Result<Dataset, String> {
+        let indices = numeric_columns(&self, columns)?;
+        let names = column_names(&self, &indices);
+        let values = stats::quantiles(self.rows, &indices, qs, method, include_nulls)?;
+        Ok(Dataset::from_quantiles(names, qs, values))
+    }
+
+    /// Summary statistics of each column, see [`stats::describe`].
+    pub fn describe<S: AsRef<str>>(self, columns: &[S]) -> Result<Dataset, String> {
+        let indices = numeric_columns(&self, columns)?;
+        let names = column_names(&self, &indices);
+        let stats = stats::describe(self.rows, &indices)?;
+        Ok(describe_table(names, stats))
+    }
+
+    /// Rows joined with the `right` table on the `on` columns, see [`join::join`].
+    pub fn join<S: AsRef<str>>(
+        self,
+        right: Dataset,
+        on: &[S],
+        how: JoinKind,
+        suffixes: (&str, &str),
+    ) -> Result<RowStream<impl Iterator<Item = Result<Vec<Value>, String>> + use<I, S>>, String>
+    {
+        join::join(self, right, on, how, suffixes)
+    }
+
+    /// A quantile sketch of each column, see [`stats::sketch`].
+    pub fn sketch<S: AsRef<str>>(self, columns: &[S]) -> Result<Vec<QuantileSketch>, String> {
+        let indices = numeric_columns(&self, columns)?;
+        stats::sketch(self.rows, &indices)
+    }
+}
+
+/// Headers of the columns at `indices`.
+fn column_names(columns: &impl Columns, indices: &[usize]) -> Vec<String> {
+    indices
+        .iter()
+        .map(|&idx| columns.headers()[idx].clone())
+        .collect()
+}
+
+/// Table with a `statistic` label column followed by one column per input column.
+fn describe_table(names: Vec<String>, stats: Vec<Vec<Value>>) -> Dataset {
+    let labels = stats::DESCRIBE_STATISTICS.map(|name| Value::Str(name.to_string()));
+    labelled_table(names, ("statistic", ColumnType::Str), labels, stats)
+}
+
+/// One row per label, holding the label and the values computed for each column.
+fn labelled_table(
+    names: Vec<String>,
+    (label, label_type): (&str, ColumnType),
+    labels: impl IntoIterator<Item = Value>,
+    values: Vec<Vec<Value>>,
+) -> Dataset {
+    let mut types = vec![label_type];
+    types.extend(names.iter().map(|_| ColumnType::Float));
+    let mut headers = vec![label.to_string()];
+    headers.extend(names);
+
+    let rows = zip(labels, values)
+        .map(|(label, values)| {
+            let mut row = vec![label];
+            row.extend(values);
+            row
+        })
+        .collect();
+
+    Dataset {
+        headers,
+        types,
+        rows,
+    }
+}
+
+fn numeric_columns<S: AsRef<str>>(
+    columns: &impl Columns,
+    names: &[S],
+) -> Result<Vec<usize>, String> {
+    names
+        .iter()
+        .map(|name| columns.numeric_column(name.as_ref()))
+        .collect()
+}
+
+/// Column lookups shared by in-memory tables and row streams.
+pub trait Columns {
+    fn headers(&self) -> &[String];
+
+    fn types(&self) -> &[ColumnType];
+
+    fn column_index(&self, name: &str) -> Option<usize> {
+        let name = name.to_lowercase();
+        self.headers().iter().position(|header| *header == name)
+    }
+
+    /// Index of a column that must exist and hold numbers.
+    fn numeric_column(&self, name: &str) -> Result<usize, String> {
+        let idx = self
+            .column_index(name)
+            .ok_or_else(|| format!("Invalid category: {name}"))?;
+
+        if !self.types()[idx].is_numeric() {
+            return Err(format!(
+                "Column '{name}' holds {} values, expected numbers",
+                self.types()[idx]
+            ));
+        }
+        Ok(idx)
+    }
+}
+
+impl Columns for Dataset {
+    fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    fn types(&self) -> &[ColumnType] {
+        &self.types
+    }
+}
+
+impl<I> Columns for RowStream<I> {
+    fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    fn types(&self) -> &[ColumnType] {
+        &self.types
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_records_infers_types() {
+        let headers = vec!["id".to_string(), "name".to_string(), "price".to_string()];
+        let records = vec![
+            vec!["1".to_string(), "apple".to_string(), "2.5".to_string()],
+            vec!["2".to_string(), "pear".to_string(), "3".to_string()],
+        ];
+
+        let table = Dataset::from_records(headers, records).unwrap();
+        assert_eq!(
+            table.types,
+            vec![ColumnType::Int, ColumnType::Str, ColumnType::Float]
+        );
+        assert_eq!(table.rows[1][2], Value::Float(3.0));
+        assert!(table.numeric_column("name").is_err());
+        assert_eq!(table.numeric_column("PRICE"), Ok(2));
+
+        let headers = vec!["id".to_string(), "name".to_string()];
+        let ragged = vec![
+            vec!["1".to_string(), "apple".to_string()],
+            vec!["2".to_string()],
+        ];
+        assert_eq!(
+            Dataset::from_records(headers, ragged).unwrap_err(),
+            "Mismatch between header count and data columns on record 2"
+        );
+    }
+
+    #[test]
+    fn test_sort_filter_and_json() {
+        let input = "id,price\n1,20\n2,15\n3,20\n4,\n";
+        let mut dataset =
+            Dataset::from_reader(input.as_bytes(), Dialect::default(), false).unwrap();
+
+        dataset.sort_by("price", true).unwrap();
+        let ids: Vec<Value> = dataset.rows.iter().map(|row| row[0].clone()).collect();
+        // Nulls go last, equal prices keep their input order.
+        assert_eq!(ids, [1, 3, 2, 4].map(Value::Int));
+
+        let streamed = dataset
+            .clone()
+            .into_stream()
+            .sort_by("price", true)
+            .unwrap();
+        assert_eq!(Dataset::from_stream(streamed).unwrap().rows, dataset.rows);
+
+        dataset.filter("price", Operator::Lt, Some("20")).unwrap();
+        dataset.filter_expr("price > 10 && id is not null").unwrap();
+        assert_eq!(
+            dataset.to_json().unwrap(),
+            "[\n  {\n    \"id\": 2,\n    \"price\": 15\n  }\n]\n"
+        );
+        assert_eq!(
+            dataset.median(&["price"]).unwrap(),
+            vec![Value::Float(15.0)]
+        );
+        assert!(dataset.sort_by("missing", false).is_err());
+    }
+
+    #[test]
+    fn test_failed_mutate_keeps_columns() {
+        let input = "id,price\n1,20\n";
+        let mut dataset =
+            Dataset::from_reader(input.as_bytes(), Dialect::default(), false).unwrap();
+
+        assert!(dataset.mutate(&["double = price * 2", "bad = ("]).is_err());
+        assert_eq!(dataset.headers, ["id", "price"]);
+        assert_eq!(dataset.types.len(), 2);
+
+        dataset.mutate(&["double = price * 2"]).unwrap();
+        assert_eq!(
+            dataset.rows[0],
+            [Value::Int(1), Value::Int(20), Value::Int(40)]
+        );
+    }
+}
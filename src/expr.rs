@@ -0,0 +1,398 @@
+use crate::{
+    dataset::Columns,
+    filter::{Operator, RowFilter},
+    value::Value,
+};
+
+/// Boolean expression over the columns of a row, e.g.
+/// `price > 15 && (quantity < 3 || id == 7)`.
+///
+/// Comparisons take a column name on the left and a number on the right.
+/// `&&`/`and` binds tighter than `||`/`or`, and `!`/`not` negates.
+/// `column is null` and `column is not null` test for empty cells.
+/// Column names that are not plain words can be written in backticks.
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Compare(RowFilter),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Parse `source`, resolving column names against `columns`.
+    /// Errors name the 1-based character position they refer to.
+    pub fn parse(source: &str, columns: &impl Columns) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: Tokens::new(source, 0)?,
+            columns,
+        };
+        let expr = parser.or()?;
+        parser.tokens.finish()?;
+        Ok(expr)
+    }
+
+    pub fn matches(&self, row: &[Value]) -> bool {
+        match self {
+            Self::Compare(filter) => filter.matches(row),
+            Self::Not(expr) => !expr.matches(row),
+            Self::And(lhs, rhs) => lhs.matches(row) && rhs.matches(row),
+            Self::Or(lhs, rhs) => lhs.matches(row) || rhs.matches(row),
+        }
+    }
+}
+
+impl From<RowFilter> for Expr {
+    fn from(filter: RowFilter) -> Self {
+        Self::Compare(filter)
+    }
+}
+
+/// A token of a `where` expression or a `mutate` formula.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// A word, or a column name written in backticks.
+    Ident(String),
+    Number(f64),
+    /// An operator or punctuation as written, with the words `and`, `or`
+    /// and `not` as `&&`, `||` and `!`.
+    Symbol(&'static str),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ident(name) => write!(f, "'{name}'"),
+            Self::Number(num) => write!(f, "number {num}"),
+            Self::Symbol(symbol) => write!(f, "'{symbol}'"),
+        }
+    }
+}
+
+/// Operators and punctuation, longest first so `>=` wins over `>`.
+const SYMBOLS: [&str; 19] = [
+    "&&", "||", ">=", "<=", "==", "!=", ">", "<", "=", "!", "(", ")", ",", "+", "-", "*", "/", "%",
+    "^",
+];
+
+pub fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+/// Split `source` into tokens, each paired with its 1-based position after
+/// the `offset` characters preceding `source`.
+fn tokenize(source: &str, offset: usize) -> Result<Vec<(usize, Token)>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let pos = offset + i + 1;
+        let next = chars.get(i + 1).copied();
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let symbol = SYMBOLS.into_iter().find(|symbol| {
+            symbol
+                .chars()
+                .enumerate()
+                .all(|(j, s)| chars.get(i + j) == Some(&s))
+        });
+        let (token, len) = match c {
+            '`' => {
+                let len = chars[i + 1..]
+                    .iter()
+                    .position(|&c| c == '`')
+                    .ok_or_else(|| format!("Unterminated column name at position {pos}"))?;
+                let name = chars[i + 1..i + 1 + len].iter().collect();
+                (Token::Ident(name), len + 2)
+            }
+            _ if c.is_ascii_digit() || (c == '.' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let len = chars[i..]
+                    .iter()
+                    .position(|&c| !(c.is_ascii_digit() || c == '.'))
+                    .unwrap_or(chars.len() - i);
+                let literal: String = chars[i..i + len].iter().collect();
+                let num = literal
+                    .parse()
+                    .map_err(|_| format!("Invalid number '{literal}' at position {pos}"))?;
+                (Token::Number(num), len)
+            }
+            _ if is_ident_char(c) => {
+                let len = chars[i..]
+                    .iter()
+                    .position(|&c| !is_ident_char(c))
+                    .unwrap_or(chars.len() - i);
+                let word: String = chars[i..i + len].iter().collect();
+                let token = match word.to_lowercase().as_str() {
+                    "and" => Token::Symbol("&&"),
+                    "or" => Token::Symbol("||"),
+                    "not" => Token::Symbol("!"),
+                    _ => Token::Ident(word),
+                };
+                (token, len)
+            }
+            _ => match symbol {
+                Some(symbol) => (Token::Symbol(symbol), symbol.len()),
+                None => return Err(format!("Unexpected character '{c}' at position {pos}")),
+            },
+        };
+
+        tokens.push((pos, token));
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+/// Tokens of a source being parsed, read front to back.
+pub struct Tokens {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    /// Position reported for errors at the end of the input.
+    end: usize,
+}
+
+impl Tokens {
+    /// Tokens of `source`, positioned after the `offset` characters
+    /// preceding it.
+    pub fn new(source: &str, offset: usize) -> Result<Self, String> {
+        Ok(Self {
+            tokens: tokenize(source, offset)?,
+            next: 0,
+            end: offset + source.chars().count() + 1,
+        })
+    }
+
+    pub fn peek(&self) -> Option<&(usize, Token)> {
+        self.tokens.get(self.next)
+    }
+
+    pub fn bump(&mut self) -> Option<(usize, Token)> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    /// Consume the next token if it is `symbol`.
+    pub fn eat(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some((_, Token::Symbol(next))) if *next == symbol);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    /// Consume the next token if it is the word `keyword`, in any case.
+    pub fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(
+            self.peek(),
+            Some((_, Token::Ident(word))) if word.eq_ignore_ascii_case(keyword)
+        );
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    pub fn expected(&self, what: &str) -> String {
+        match self.peek() {
+            Some((pos, token)) => format!("Expected {what} at position {pos}, found {token}"),
+            None => format!("Expected {what} at position {}", self.end),
+        }
+    }
+
+    /// Fail on any token left over once parsing is done.
+    pub fn finish(&self) -> Result<(), String> {
+        match self.peek() {
+            None => Ok(()),
+            Some((pos, token)) => Err(format!("Unexpected {token} at position {pos}")),
+        }
+    }
+}
+
+struct Parser<'a, C> {
+    tokens: Tokens,
+    columns: &'a C,
+}
+
+impl<C: Columns> Parser<'_, C> {
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.tokens.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.tokens.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.tokens.eat("!") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        if self.tokens.eat("(") {
+            let expr = self.or()?;
+            if !self.tokens.eat(")") {
+                return Err(self.tokens.expected("')'"));
+            }
+            return Ok(expr);
+        }
+
+        let (pos, name) = match self.tokens.peek() {
+            Some((pos, Token::Ident(name))) => (*pos, name.clone()),
+            _ => return Err(self.tokens.expected("a column name")),
+        };
+        self.tokens.bump();
+        if self.columns.column_index(&name).is_none() {
+            return Err(format!("Unknown column '{name}' at position {pos}"));
+        }
+
+        let comparison = match self.tokens.peek() {
+            Some((_, Token::Symbol(symbol))) => comparison(symbol),
+            _ => None,
+        };
+        let (operator, argument) = if let Some(operator) = comparison {
+            self.tokens.bump();
+            (operator, Some(self.number()?.to_string()))
+        } else if self.tokens.eat_keyword("is") {
+            let operator = if self.tokens.eat("!") {
+                Operator::NotNull
+            } else {
+                Operator::IsNull
+            };
+            if !self.tokens.eat_keyword("null") {
+                return Err(self.tokens.expected("'null'"));
+            }
+            (operator, None)
+        } else {
+            return Err(self
+                .tokens
+                .expected(&format!("a comparison after '{name}'")));
+        };
+
+        RowFilter::new(self.columns, &name, operator, argument.as_deref(), false)
+            .map(Expr::Compare)
+            .map_err(|err| format!("{err} at position {pos}"))
+    }
+
+    /// A number, maybe negative.
+    fn number(&mut self) -> Result<f64, String> {
+        let negative = self.tokens.eat("-");
+        match self.tokens.peek() {
+            Some((_, Token::Number(num))) => {
+                let num = if negative { -num } else { *num };
+                self.tokens.bump();
+                Ok(num)
+            }
+            _ => Err(self.tokens.expected("a number")),
+        }
+    }
+}
+
+/// Filter operator of a comparison symbol.
+fn comparison(symbol: &str) -> Option<Operator> {
+    Some(match symbol {
+        ">" => Operator::Gt,
+        ">=" => Operator::Gte,
+        "<" => Operator::Lt,
+        "<=" => Operator::Lte,
+        "==" | "=" => Operator::Eq,
+        "!=" => Operator::Neq,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::Dataset;
+
+    fn dataset() -> Dataset {
+        let records = [[1, 20, 7], [2, 15, 3], [3, 16, 2], [7, 10, 9]]
+            .iter()
+            .map(|row| row.iter().map(|n| n.to_string()).collect())
+            .chain([vec!["8".to_string(), "30".to_string(), "".to_string()]])
+            .collect();
+        let headers = ["id", "price", "quantity"].map(String::from).to_vec();
+        Dataset::from_records(headers, records).unwrap()
+    }
+
+    fn matching_ids(source: &str) -> Vec<i64> {
+        let dataset = dataset();
+        let expr = Expr::parse(source, &dataset).unwrap();
+        dataset
+            .rows
+            .iter()
+            .filter(|row| expr.matches(row))
+            .map(|row| match row[0] {
+                Value::Int(id) => id,
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_precedence_and_grouping() {
+        assert_eq!(matching_ids("price > 15 && quantity <= 4"), vec![3]);
+        assert_eq!(
+            matching_ids("price > 15 and (quantity < 3 or id == 7)"),
+            vec![3]
+        );
+        assert_eq!(
+            matching_ids("price > 15 && quantity < 3 || id == 7"),
+            vec![3, 7]
+        );
+        assert_eq!(matching_ids("!(price >= 16) || PRICE = -1"), vec![2, 7]);
+        assert_eq!(
+            matching_ids("quantity is null or id != 1.5"),
+            vec![1, 2, 3, 7, 8]
+        );
+        assert_eq!(matching_ids("not `quantity` is not null"), vec![8]);
+    }
+
+    #[test]
+    fn test_errors_report_positions() {
+        let dataset = dataset();
+        let error = |source| Expr::parse(source, &dataset).unwrap_err();
+
+        assert_eq!(
+            error("price > 15 && quantiy <= 4"),
+            "Unknown column 'quantiy' at position 15"
+        );
+        assert_eq!(error("(price > 15"), "Expected ')' at position 12");
+        assert_eq!(
+            error("price > && id < 3"),
+            "Expected a number at position 9, found '&&'"
+        );
+        assert_eq!(
+            error("price 15"),
+            "Expected a comparison after 'price' at position 7, found number 15"
+        );
+        assert_eq!(error("id < 3 $"), "Unexpected character '$' at position 8");
+        assert_eq!(error("id < 3 )"), "Unexpected ')' at position 8");
+        // Arithmetic is for mutate formulas only.
+        assert_eq!(
+            error("id + 1 > 2"),
+            "Expected a comparison after 'id' at position 4, found '+'"
+        );
+        assert_eq!(
+            error("id >> 3"),
+            "Expected a number at position 5, found '>'"
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    rc::Rc,
+    vec,
+};
+
+use tempfile::{NamedTempFile, TempPath};
+
+use crate::{
+    csv::{CsvReader, Dialect, write_record},
+    value::{Date, Value},
+};
+
+/// Rows kept in memory before a sorted run is spilled to a temporary file.
+pub const SORT_CHUNK_ROWS: usize = 100_000;
+
+/// Runs read by one merge, which bounds the files open at once.
+const MERGE_FAN_IN: usize = 64;
+
+/// Stable external merge sort over typed rows.
+///
+/// Rows are buffered until `chunk_rows` is reached, then the sorted chunk is
+/// written to a temporary file. Inputs that fit in one chunk never touch the
+/// disk. Runs are merged at most [`MERGE_FAN_IN`] at a time, in as many
+/// passes as needed.
+pub struct ExternalSorter<F> {
+    chunk_rows: usize,
+    cmp: Rc<F>,
+    buffer: Vec<Vec<Value>>,
+    runs: Vec<TempPath>,
+    len: usize,
+}
+
+impl<F> ExternalSorter<F>
+where
+    F: Fn(&[Value], &[Value]) -> Ordering,
+{
+    pub fn new(chunk_rows: usize, cmp: F) -> Self {
+        Self {
+            chunk_rows: chunk_rows.max(1),
+            cmp: Rc::new(cmp),
+            buffer: Vec::new(),
+            runs: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, row: Vec<Value>) -> Result<(), String> {
+        self.buffer.push(row);
+        self.len += 1;
+        if self.buffer.len() >= self.chunk_rows {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    fn spill(&mut self) -> Result<(), String> {
+        let cmp = &self.cmp;
+        self.buffer.sort_by(|a, b| cmp(a, b));
+        let run = write_run(self.buffer.drain(..).map(Ok))?;
+        self.runs.push(run);
+        Ok(())
+    }
+
+    /// Sort the remaining rows and merge all runs.
+    pub fn finish(mut self) -> Result<SortedRows<F>, String> {
+        let cmp = &self.cmp;
+        self.buffer.sort_by(|a, b| cmp(a, b));
+
+        // Merge consecutive runs into longer ones until the last merge can
+        // read all of them next to the rows still in memory. Groups keep
+        // their order so equal rows keep their input order.
+        let mut runs = self.runs;
+        while runs.len() >= MERGE_FAN_IN {
+            let mut merged = Vec::with_capacity(runs.len().div_ceil(MERGE_FAN_IN));
+            for group in runs.chunks(MERGE_FAN_IN) {
+                let sources = group.iter().map(Source::open).collect::<Result<_, _>>()?;
+                let rows = SortedRows::new(Rc::clone(&self.cmp), sources, Vec::new())?;
+                merged.push(write_run(rows)?);
+            }
+            runs = merged;
+        }
+
+        let mut sources = runs
+            .iter()
+            .map(Source::open)
+            .collect::<Result<Vec<_>, _>>()?;
+        sources.push(Source::Memory(self.buffer.into_iter()));
+        SortedRows::new(self.cmp, sources, runs)
+    }
+}
+
+/// Write sorted `rows` to a new temporary file.
+fn write_run(rows: impl Iterator<Item = Result<Vec<Value>, String>>) -> Result<TempPath, String> {
+    let (file, path) = NamedTempFile::new()
+        .map_err(|err| format!("Create sort run: {err}"))?
+        .into_parts();
+    let mut writer = BufWriter::new(file);
+    for row in rows {
+        write_record(&mut writer, row?.iter().map(encode), &Dialect::default())
+            .map_err(|err| format!("Write sort run: {err}"))?;
+    }
+    writer
+        .flush()
+        .map_err(|err| format!("Write sort run: {err}"))?;
+    Ok(path)
+}
+
+/// A cell of a run: a letter naming the kind of value, then its text, so
+/// that every value reads back exactly as it was pushed.
+fn encode(value: &Value) -> String {
+    match value {
+        Value::Null => "n".to_string(),
+        Value::Int(i) => format!("i{i}"),
+        Value::Float(x) => format!("f{x}"),
+        Value::Bool(b) => format!("b{b}"),
+        Value::Date(d) => format!("d{d}"),
+        Value::Str(s) => format!("s{s}"),
+    }
+}
+
+fn decode(cell: &str) -> Result<Value, String> {
+    let corrupt = || format!("Read sort run: unexpected cell '{cell}'");
+    let (kind, text) = cell.split_at_checked(1).ok_or_else(corrupt)?;
+    let value = match kind {
+        "n" if text.is_empty() => Value::Null,
+        "i" => Value::Int(text.parse().map_err(|_| corrupt())?),
+        "f" => Value::Float(text.parse().map_err(|_| corrupt())?),
+        "b" => Value::Bool(text.parse().map_err(|_| corrupt())?),
+        "d" => Value::Date(Date::parse(text).ok_or_else(corrupt)?),
+        "s" => Value::Str(text.to_string()),
+        _ => return Err(corrupt()),
+    };
+    Ok(value)
+}
+
+enum Source {
+    Run(CsvReader<BufReader<File>>),
+    Memory(vec::IntoIter<Vec<Value>>),
+}
+
+impl Source {
+    fn open(path: &TempPath) -> Result<Self, String> {
+        let file = File::open(path).map_err(|err| format!("Open sort run: {err}"))?;
+        Ok(Self::Run(CsvReader::new(
+            BufReader::new(file),
+            Dialect::default(),
+        )))
+    }
+
+    fn next_row(&mut self) -> Option<Result<Vec<Value>, String>> {
+        match self {
+            Self::Run(reader) => Some(match reader.next()? {
+                Ok(record) => record.iter().map(|cell| decode(cell)).collect(),
+                Err(err) => Err(format!("Read sort run: {err}")),
+            }),
+            Self::Memory(rows) => rows.next().map(Ok),
+        }
+    }
+}
+
+/// The next row of one source, waiting in the merge heap.
+struct Head<F> {
+    row: Vec<Value>,
+    source: usize,
+    cmp: Rc<F>,
+}
+
+impl<F> Ord for Head<F>
+where
+    F: Fn(&[Value], &[Value]) -> Ordering,
+{
+    // The heap pops its largest entry, so the order is reversed: the
+    // smallest row comes out first, and the earlier source wins ties.
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.cmp)(&other.row, &self.row).then(other.source.cmp(&self.source))
+    }
+}
+
+impl<F> PartialOrd for Head<F>
+where
+    F: Fn(&[Value], &[Value]) -> Ordering,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<F> PartialEq for Head<F>
+where
+    F: Fn(&[Value], &[Value]) -> Ordering,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<F> Eq for Head<F> where F: Fn(&[Value], &[Value]) -> Ordering {}
+
+/// Rows of an [`ExternalSorter`] in sorted order.
+pub struct SortedRows<F> {
+    cmp: Rc<F>,
+    sources: Vec<Source>,
+    heads: BinaryHeap<Head<F>>,
+    // Removed once the merge is dropped.
+    _runs: Vec<TempPath>,
+}
+
+impl<F> SortedRows<F>
+where
+    F: Fn(&[Value], &[Value]) -> Ordering,
+{
+    fn new(cmp: Rc<F>, mut sources: Vec<Source>, runs: Vec<TempPath>) -> Result<Self, String> {
+        let mut heads = BinaryHeap::with_capacity(sources.len());
+        for (source, rows) in sources.iter_mut().enumerate() {
+            if let Some(row) = rows.next_row().transpose()? {
+                heads.push(Head {
+                    row,
+                    source,
+                    cmp: Rc::clone(&cmp),
+                });
+            }
+        }
+        Ok(Self {
+            cmp,
+            sources,
+            heads,
+            _runs: runs,
+        })
+    }
+}
+
+impl<F> Iterator for SortedRows<F>
+where
+    F: Fn(&[Value], &[Value]) -> Ordering,
+{
+    type Item = Result<Vec<Value>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Head { row, source, .. } = self.heads.pop()?;
+        match self.sources[source].next_row() {
+            Some(Ok(next)) => self.heads.push(Head {
+                row: next,
+                source,
+                cmp: Rc::clone(&self.cmp),
+            }),
+            Some(Err(err)) => return Some(Err(err)),
+            None => {}
+        }
+        Some(Ok(row))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_spills_and_stays_stable() {
+        let mut sorter = ExternalSorter::new(3, |a: &[Value], b: &[Value]| a[0].total_cmp(&b[0]));
+
+        let keys = [5, 1, 3, 1, 4, 3, 2, 5, 1, 0];
+        for (i, key) in keys.iter().enumerate() {
+            let label = if i == 2 {
+                Value::Null
+            } else {
+                Value::Str(format!("row {i}, key {key}"))
+            };
+            sorter.push(vec![Value::Int(*key), label]).unwrap();
+        }
+        assert_eq!(sorter.len(), keys.len());
+
+        let rows = sorter
+            .finish()
+            .unwrap()
+            .collect::<Result<Vec<_>, String>>()
+            .unwrap();
+        let sorted_keys: Vec<Value> = rows.iter().map(|row| row[0].clone()).collect();
+        let mut expected = keys.to_vec();
+        expected.sort();
+        assert_eq!(
+            sorted_keys,
+            expected.into_iter().map(Value::Int).collect::<Vec<_>>()
+        );
+
+        // Rows with equal keys keep their input order across runs.
+        assert_eq!(rows[1][1], Value::Str("row 1, key 1".into()));
+        assert_eq!(rows[2][1], Value::Str("row 3, key 1".into()));
+        assert_eq!(rows[3][1], Value::Str("row 8, key 1".into()));
+        assert_eq!(rows[5][1], Value::Null);
+    }
+
+    #[test]
+    fn test_many_runs_keep_exact_cells() {
+        let cells = [
+            Value::Str("  padded ".into()),
+            Value::Str(String::new()),
+            Value::Null,
+            Value::Str("a, \"quoted\"\nline".into()),
+            Value::Float(0.1),
+            Value::Bool(true),
+            Value::Date(Date::parse("2024-02-29").unwrap()),
+        ];
+        // One row per run, so several merge passes are needed.
+        let mut sorter = ExternalSorter::new(1, |a: &[Value], b: &[Value]| a[0].total_cmp(&b[0]));
+        let count = MERGE_FAN_IN * 3 + 5;
+        for i in 0..count {
+            let key = Value::Int((i % 7) as i64);
+            sorter
+                .push(vec![
+                    key,
+                    cells[i % cells.len()].clone(),
+                    Value::Int(i as i64),
+                ])
+                .unwrap();
+        }
+
+        let rows = sorter
+            .finish()
+            .unwrap()
+            .collect::<Result<Vec<_>, String>>()
+            .unwrap();
+        assert_eq!(rows.len(), count);
+        for pair in rows.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            assert!(a[0].total_cmp(&b[0]).then(a[2].total_cmp(&b[2])).is_lt());
+        }
+        for row in &rows {
+            let Value::Int(i) = row[2] else {
+                panic!("row number lost: {row:?}");
+            };
+            assert_eq!(row[1], cells[i as usize % cells.len()]);
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use std::{cmp::Ordering, fmt};
+
+use clap::ValueEnum;
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    dataset::Columns,
+    value::{ColumnType, Value},
+};
+
+/// Comparison applied by [`RowFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Operator {
+    /// Greater than
+    Gt,
+    /// Greater than or Equal
+    Gte,
+    /// Less than
+    Lt,
+    /// Less than or Equal
+    Lte,
+    /// Equal to
+    Eq,
+    /// Not Equal to
+    Neq,
+    /// Cell is empty
+    IsNull,
+    /// Cell is not empty
+    NotNull,
+    /// Text contains VALUE
+    Contains,
+    /// Text starts with VALUE
+    StartsWith,
+    /// Text ends with VALUE
+    EndsWith,
+    /// Text matches the regular expression VALUE
+    Matches,
+    /// Cell is one of the comma separated values in VALUE, e.g. a,b,c
+    In,
+}
+
+/// The name the operator is written as on the command line, e.g. `gt`.
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_possible_value() {
+            Some(value) => f.write_str(value.get_name()),
+            None => write!(f, "{self:?}"),
+        }
+    }
+}
+
+/// What a cell is tested against.
+#[derive(Debug, Clone)]
+enum Argument {
+    None,
+    Number(f64),
+    Text(String),
+    Regex(Regex),
+    Set(Vec<Value>),
+}
+
+/// A `COLUMN OPERATOR VALUE` criterion resolved against a set of columns.
+#[derive(Debug, Clone)]
+pub struct RowFilter {
+    column: usize,
+    operator: Operator,
+    argument: Argument,
+    ignore_case: bool,
+}
+
+impl RowFilter {
+    /// Comparisons need a numeric column and a number, the text operators a
+    /// column that does not hold numbers. `in` works on any column, its values
+    /// must fit the column type. `is-null` and `not-null` work on any column
+    /// and ignore the argument.
+    pub fn new(
+        columns: &impl Columns,
+        column: &str,
+        operator: Operator,
+        argument: Option<&str>,
+        ignore_case: bool,
+    ) -> Result<Self, String> {
+        let require_argument =
+            || argument.ok_or_else(|| format!("Operator {operator} requires a VALUE to compare"));
+
+        let (column, argument) = match operator {
+            Operator::IsNull | Operator::NotNull => (find_column(columns, column)?, Argument::None),
+            Operator::Gt
+            | Operator::Gte
+            | Operator::Lt
+            | Operator::Lte
+            | Operator::Eq
+            | Operator::Neq => {
+                let idx = columns.numeric_column(column)?;
+                let argument = require_argument()?;
+                let number = argument.trim().parse().map_err(|_| {
+                    format!("Operator {operator} expects a number, got '{argument}'")
+                })?;
+                (idx, Argument::Number(number))
+            }
+            Operator::Contains | Operator::StartsWith | Operator::EndsWith | Operator::Matches => {
+                let idx = text_column(columns, column, operator)?;
+                let argument = require_argument()?;
+                let argument = match operator {
+                    Operator::Matches => Argument::Regex(
+                        RegexBuilder::new(argument)
+                            .case_insensitive(ignore_case)
+                            .build()
+                            .map_err(|err| format!("Invalid regular expression: {err}"))?,
+                    ),
+                    _ if ignore_case => Argument::Text(argument.to_lowercase()),
+                    _ => Argument::Text(argument.to_string()),
+                };
+                (idx, argument)
+            }
+            Operator::In => {
+                let idx = find_column(columns, column)?;
+                let ty = columns.types()[idx];
+                let set = require_argument()?
+                    .split(',')
+                    .map(|item| set_value(item, ty, ignore_case))
+                    .collect::<Result<_, String>>()?;
+                (idx, Argument::Set(set))
+            }
+        };
+
+        Ok(Self {
+            column,
+            operator,
+            argument,
+            ignore_case,
+        })
+    }
+
+    pub fn matches(&self, row: &[Value]) -> bool {
+        let value = &row[self.column];
+        match self.operator {
+            Operator::IsNull => return value.is_null(),
+            Operator::NotNull => return !value.is_null(),
+            // Nulls never satisfy a comparison, not even `neq`.
+            _ if value.is_null() => return false,
+            _ => {}
+        }
+
+        match &self.argument {
+            Argument::Number(argument) => {
+                let Some(value) = value.as_f64() else {
+                    return false;
+                };
+                match self.operator {
+                    Operator::Gt => value > *argument,
+                    Operator::Lt => value < *argument,
+                    Operator::Eq => (value - argument).abs() < f64::EPSILON,
+                    Operator::Neq => (value - argument).abs() > f64::EPSILON,
+                    Operator::Gte => value >= *argument,
+                    Operator::Lte => value <= *argument,
+                    _ => false,
+                }
+            }
+            Argument::Text(needle) => {
+                let text = self.text(value);
+                match self.operator {
+                    Operator::Contains => text.contains(needle.as_str()),
+                    Operator::StartsWith => text.starts_with(needle.as_str()),
+                    Operator::EndsWith => text.ends_with(needle.as_str()),
+                    _ => false,
+                }
+            }
+            Argument::Regex(regex) => regex.is_match(&value.to_string()),
+            Argument::Set(set) => match value {
+                Value::Str(_) => {
+                    let text = Value::Str(self.text(value));
+                    set.contains(&text)
+                }
+                _ => set
+                    .iter()
+                    .any(|item| value.total_cmp(item) == Ordering::Equal),
+            },
+            Argument::None => false,
+        }
+    }
+
+    fn text(&self, value: &Value) -> String {
+        if self.ignore_case {
+            value.to_string().to_lowercase()
+        } else {
+            value.to_string()
+        }
+    }
+}
+
+fn find_column(columns: &impl Columns, name: &str) -> Result<usize, String> {
+    columns
+        .column_index(name)
+        .ok_or_else(|| format!("Invalid category: {name}"))
+}
+
+/// Index of a column the text operators can run on.
+fn text_column(columns: &impl Columns, name: &str, operator: Operator) -> Result<usize, String> {
+    let idx = find_column(columns, name)?;
+    let ty = columns.types()[idx];
+    if ty.is_numeric() {
+        return Err(format!(
+            "Operator {operator} needs a text column, '{name}' holds {ty} values"
+        ));
+    }
+    Ok(idx)
+}
+
+/// One value of an `in` set, parsed with the column type.
+fn set_value(item: &str, ty: ColumnType, ignore_case: bool) -> Result<Value, String> {
+    match ty.try_parse(item) {
+        Some(Value::Null) => Err("Empty value in set".to_string()),
+        Some(Value::Str(text)) if ignore_case => Ok(Value::Str(text.to_lowercase())),
+        Some(value) => Ok(value),
+        None => Err(format!("'{}' is not a valid {ty}", item.trim())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::Dataset;
+
+    fn dataset() -> Dataset {
+        let records = [
+            ["1", "Apple pie", "2024-01-05"],
+            ["2", "banana", "2024-02-10"],
+            ["3", "", "2023-12-31"],
+            ["4", "pineapple", "2024-01-20"],
+        ]
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+        .collect();
+        let headers = ["id", "name", "day"].map(String::from).to_vec();
+        Dataset::from_records(headers, records).unwrap()
+    }
+
+    fn matching_ids(
+        operator: Operator,
+        column: &str,
+        argument: &str,
+        ignore_case: bool,
+    ) -> Vec<i64> {
+        let dataset = dataset();
+        let filter =
+            RowFilter::new(&dataset, column, operator, Some(argument), ignore_case).unwrap();
+        dataset
+            .rows
+            .iter()
+            .filter(|row| filter.matches(row))
+            .map(|row| match row[0] {
+                Value::Int(id) => id,
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_text_operators() {
+        assert_eq!(
+            matching_ids(Operator::Contains, "name", "apple", false),
+            vec![4]
+        );
+        assert_eq!(
+            matching_ids(Operator::Contains, "name", "APPLE", true),
+            vec![1, 4]
+        );
+        assert_eq!(
+            matching_ids(Operator::StartsWith, "name", "b", false),
+            vec![2]
+        );
+        assert_eq!(
+            matching_ids(Operator::EndsWith, "name", "PIE", true),
+            vec![1]
+        );
+        assert_eq!(
+            matching_ids(Operator::StartsWith, "day", "2024-01", false),
+            vec![1, 4]
+        );
+        assert_eq!(
+            matching_ids(Operator::Matches, "name", "^[a-p]+$", false),
+            vec![2, 4]
+        );
+        assert_eq!(matching_ids(Operator::Matches, "name", "^a", true), vec![1]);
+    }
+
+    #[test]
+    fn test_set_membership() {
+        assert_eq!(
+            matching_ids(Operator::In, "id", "1, 3,4", false),
+            vec![1, 3, 4]
+        );
+        assert_eq!(
+            matching_ids(Operator::In, "name", "banana,apple pie", false),
+            vec![2]
+        );
+        assert_eq!(
+            matching_ids(Operator::In, "name", "banana,apple pie", true),
+            vec![1, 2]
+        );
+        assert_eq!(
+            matching_ids(Operator::In, "day", "2023-12-31", false),
+            vec![3]
+        );
+    }
+
+    #[test]
+    fn test_operator_must_fit_column_type() {
+        let dataset = dataset();
+        let error = |column, operator, argument| {
+            RowFilter::new(&dataset, column, operator, Some(argument), false).unwrap_err()
+        };
+
+        assert_eq!(
+            error("id", Operator::Contains, "1"),
+            "Operator contains needs a text column, 'id' holds integer values"
+        );
+        assert_eq!(
+            error("name", Operator::Gt, "1"),
+            "Column 'name' holds string values, expected numbers"
+        );
+        assert_eq!(
+            error("id", Operator::Gt, "one"),
+            "Operator gt expects a number, got 'one'"
+        );
+        assert_eq!(
+            RowFilter::new(&dataset, "name", Operator::StartsWith, None, false).unwrap_err(),
+            "Operator starts-with requires a VALUE to compare"
+        );
+        assert_eq!(
+            error("id", Operator::In, "1,x"),
+            "'x' is not a valid integer"
+        );
+        assert!(error("name", Operator::Matches, "(").starts_with("Invalid regular expression"));
+        for operator in [Operator::IsNull, Operator::In, Operator::Contains] {
+            assert_eq!(error("nme", operator, "x"), "Invalid category: nme");
+        }
+    }
+}
@@ -0,0 +1,639 @@
+use std::str::FromStr;
+
+use crate::{
+    dataset::Columns,
+    expr::{Token, Tokens, is_ident_char},
+    value::{ColumnType, Value},
+};
+
+/// A computed column, written `NAME = EXPRESSION`, e.g. `total = price * quantity`.
+///
+/// Expressions combine numeric columns and numbers with `+ - * / % ^` and
+/// parentheses, the functions listed in [`Function`], comparisons
+/// (`> >= < <= == !=`) and `&&`/`and`, `||`/`or`, `!`/`not`, which yield 1
+/// for true and 0 for false. `if(cond, a, b)` picks `a` when `cond` is not 0.
+/// A null operand, a division by zero or a result that is not a finite
+/// number gives a null cell.
+///
+/// Results are integers when every operand is an integer column or literal
+/// and only `+ - * %` and negation combine them, and floats otherwise.
+#[derive(Debug, Clone)]
+pub struct Formula {
+    pub name: String,
+    pub ty: ColumnType,
+    expression: Node,
+}
+
+impl Formula {
+    /// Parse `source`, resolving column names against `columns`.
+    /// Errors name the 1-based character position they refer to.
+    pub fn parse(source: &str, columns: &impl Columns) -> Result<Self, String> {
+        let (name, expression) = source
+            .split_once('=')
+            .filter(|(_, expression)| !expression.starts_with('='))
+            .ok_or_else(|| format!("Expected NAME = EXPRESSION, got '{source}'"))?;
+        let name = name.trim().to_lowercase();
+        if name.is_empty() || !name.chars().all(is_ident_char) {
+            return Err(format!("Invalid column name '{name}'"));
+        }
+
+        // Positions count from the start of `source`.
+        let offset = source.len() - expression.len();
+        let mut parser = Parser {
+            tokens: Tokens::new(expression, source[..offset].chars().count())?,
+            columns,
+        };
+        let expression = parser.or()?;
+        parser.tokens.finish()?;
+        Ok(Self {
+            name,
+            ty: expression.result_type(columns.types()),
+            expression,
+        })
+    }
+
+    pub fn evaluate(&self, row: &[Value]) -> Value {
+        // Integer columns may still hold floats met past the type sample,
+        // which fall back to float arithmetic.
+        if self.ty == ColumnType::Int
+            && let Some(int) = self.expression.evaluate_int(row)
+        {
+            return Value::Int(int);
+        }
+        match self.expression.evaluate(row) {
+            Some(num) if num.is_finite() => Value::Float(num),
+            _ => Value::Null,
+        }
+    }
+}
+
+/// Functions callable in a [`Formula`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Abs,
+    Sqrt,
+    /// Natural logarithm, or `log(x, base)`
+    Log,
+    Exp,
+    /// `round(x)` or `round(x, digits)`
+    Round,
+    Floor,
+    Ceil,
+    /// Smallest of one or more values
+    Min,
+    /// Largest of one or more values
+    Max,
+    /// `if(cond, a, b)`
+    If,
+}
+
+impl FromStr for Function {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "abs" => Self::Abs,
+            "sqrt" => Self::Sqrt,
+            "log" => Self::Log,
+            "exp" => Self::Exp,
+            "round" => Self::Round,
+            "floor" => Self::Floor,
+            "ceil" => Self::Ceil,
+            "min" => Self::Min,
+            "max" => Self::Max,
+            "if" => Self::If,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl Function {
+    /// Smallest and largest number of arguments.
+    fn arity(self) -> (usize, usize) {
+        match self {
+            Self::Log | Self::Round => (1, 2),
+            Self::Min | Self::Max => (1, usize::MAX),
+            Self::If => (3, 3),
+            _ => (1, 1),
+        }
+    }
+
+    fn apply(self, args: &[f64]) -> f64 {
+        match self {
+            Self::Abs => args[0].abs(),
+            Self::Sqrt => args[0].sqrt(),
+            Self::Log => match args.get(1) {
+                Some(base) => args[0].log(*base),
+                None => args[0].ln(),
+            },
+            Self::Exp => args[0].exp(),
+            Self::Round => {
+                let scale = 10f64.powf(args.get(1).copied().unwrap_or(0.0).trunc());
+                (args[0] * scale).round() / scale
+            }
+            Self::Floor => args[0].floor(),
+            Self::Ceil => args[0].ceil(),
+            Self::Min => args.iter().copied().fold(f64::INFINITY, f64::min),
+            Self::Max => args.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Self::If => unreachable!("if is evaluated lazily"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Eq,
+    Neq,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    /// How the operator is written in a formula.
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Rem => "%",
+            Self::Pow => "^",
+            Self::Gt => ">",
+            Self::Gte => ">=",
+            Self::Lt => "<",
+            Self::Lte => "<=",
+            Self::Eq => "==",
+            Self::Neq => "!=",
+            Self::And => "&&",
+            Self::Or => "||",
+        }
+    }
+
+    fn apply(self, a: f64, b: f64) -> Option<f64> {
+        let truth = |cond: bool| if cond { 1.0 } else { 0.0 };
+        Some(match self {
+            Self::Add => a + b,
+            Self::Sub => a - b,
+            Self::Mul => a * b,
+            Self::Div if b == 0.0 => return None,
+            Self::Div => a / b,
+            Self::Rem if b == 0.0 => return None,
+            Self::Rem => a % b,
+            Self::Pow => a.powf(b),
+            Self::Gt => truth(a > b),
+            Self::Gte => truth(a >= b),
+            Self::Lt => truth(a < b),
+            Self::Lte => truth(a <= b),
+            Self::Eq => truth((a - b).abs() < f64::EPSILON),
+            Self::Neq => truth((a - b).abs() > f64::EPSILON),
+            Self::And => truth(a != 0.0 && b != 0.0),
+            Self::Or => truth(a != 0.0 || b != 0.0),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Number(f64),
+    Column(usize),
+    Negate(Box<Node>),
+    Not(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+impl Node {
+    fn result_type(&self, types: &[ColumnType]) -> ColumnType {
+        use BinaryOp::*;
+        let int = match self {
+            Self::Number(num) => num.fract() == 0.0 && num.abs() < i64::MAX as f64,
+            Self::Column(idx) => types[*idx] == ColumnType::Int,
+            Self::Negate(node) => node.result_type(types) == ColumnType::Int,
+            Self::Binary(Add | Sub | Mul | Rem, lhs, rhs) => {
+                lhs.result_type(types) == ColumnType::Int
+                    && rhs.result_type(types) == ColumnType::Int
+            }
+            _ => false,
+        };
+        if int {
+            ColumnType::Int
+        } else {
+            ColumnType::Float
+        }
+    }
+
+    /// Exact value of a node typed as an integer, none when a cell is not an
+    /// integer or the arithmetic overflows.
+    fn evaluate_int(&self, row: &[Value]) -> Option<i64> {
+        match self {
+            Self::Number(num) => Some(*num as i64),
+            Self::Column(idx) => match row[*idx] {
+                Value::Int(int) => Some(int),
+                _ => None,
+            },
+            Self::Negate(node) => node.evaluate_int(row)?.checked_neg(),
+            Self::Binary(op, lhs, rhs) => {
+                let (a, b) = (lhs.evaluate_int(row)?, rhs.evaluate_int(row)?);
+                match op {
+                    BinaryOp::Add => a.checked_add(b),
+                    BinaryOp::Sub => a.checked_sub(b),
+                    BinaryOp::Mul => a.checked_mul(b),
+                    BinaryOp::Rem => a.checked_rem(b),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn evaluate(&self, row: &[Value]) -> Option<f64> {
+        match self {
+            Self::Number(num) => Some(*num),
+            Self::Column(idx) => row[*idx].as_f64(),
+            Self::Negate(node) => node.evaluate(row).map(|num| -num),
+            Self::Not(node) => node
+                .evaluate(row)
+                .map(|num| if num == 0.0 { 1.0 } else { 0.0 }),
+            Self::Binary(op, lhs, rhs) => op.apply(lhs.evaluate(row)?, rhs.evaluate(row)?),
+            Self::Call(Function::If, args) => match args[0].evaluate(row)? {
+                cond if cond != 0.0 => args[1].evaluate(row),
+                _ => args[2].evaluate(row),
+            },
+            Self::Call(function, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.evaluate(row))
+                    .collect::<Option<Vec<_>>>()?;
+                Some(function.apply(&args))
+            }
+        }
+    }
+}
+
+struct Parser<'a, C> {
+    tokens: Tokens,
+    columns: &'a C,
+}
+
+impl<C: Columns> Parser<'_, C> {
+    /// Consume the next token if it is one of the binary operators `ops`.
+    fn eat_op(&mut self, ops: &[BinaryOp]) -> Option<BinaryOp> {
+        let op = match self.tokens.peek() {
+            Some((_, Token::Symbol(symbol))) => ops.iter().find(|op| op.symbol() == *symbol),
+            _ => None,
+        }
+        .copied();
+        if op.is_some() {
+            self.tokens.bump();
+        }
+        op
+    }
+
+    /// Left-associative chain of `ops` between operands parsed by `operand`.
+    fn chain(
+        &mut self,
+        ops: &[BinaryOp],
+        operand: fn(&mut Self) -> Result<Node, String>,
+    ) -> Result<Node, String> {
+        let mut node = operand(self)?;
+        while let Some(op) = self.eat_op(ops) {
+            node = Node::Binary(op, Box::new(node), Box::new(operand(self)?));
+        }
+        Ok(node)
+    }
+
+    fn or(&mut self) -> Result<Node, String> {
+        self.chain(&[BinaryOp::Or], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Node, String> {
+        self.chain(&[BinaryOp::And], Self::not)
+    }
+
+    fn not(&mut self) -> Result<Node, String> {
+        if self.tokens.eat("!") {
+            return Ok(Node::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Node, String> {
+        use BinaryOp::*;
+        self.chain(&[Gt, Gte, Lt, Lte, Eq, Neq], Self::additive)
+    }
+
+    fn additive(&mut self) -> Result<Node, String> {
+        self.chain(&[BinaryOp::Add, BinaryOp::Sub], Self::multiplicative)
+    }
+
+    fn multiplicative(&mut self) -> Result<Node, String> {
+        use BinaryOp::*;
+        self.chain(&[Mul, Div, Rem], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.eat_op(&[BinaryOp::Sub]).is_some() {
+            return Ok(Node::Negate(Box::new(self.unary()?)));
+        }
+        if self.eat_op(&[BinaryOp::Add]).is_some() {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    /// `^` binds tighter than unary minus and groups to the right.
+    fn power(&mut self) -> Result<Node, String> {
+        let base = self.primary()?;
+        if self.eat_op(&[BinaryOp::Pow]).is_some() {
+            let exponent = self.unary()?;
+            return Ok(Node::Binary(
+                BinaryOp::Pow,
+                Box::new(base),
+                Box::new(exponent),
+            ));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Node, String> {
+        if self.tokens.eat("(") {
+            let node = self.or()?;
+            if !self.tokens.eat(")") {
+                return Err(self.tokens.expected("')'"));
+            }
+            return Ok(node);
+        }
+
+        let (pos, name) = match self.tokens.peek() {
+            Some((_, Token::Number(num))) => {
+                let num = *num;
+                self.tokens.bump();
+                return Ok(Node::Number(num));
+            }
+            Some((pos, Token::Ident(name))) => (*pos, name.clone()),
+            _ => return Err(self.tokens.expected("a number, column or function")),
+        };
+        self.tokens.bump();
+
+        if self.tokens.eat("(") {
+            let function: Function = name
+                .parse()
+                .map_err(|_| format!("Unknown function '{name}' at position {pos}"))?;
+            return self.call(function, &name, pos);
+        }
+
+        if self.columns.column_index(&name).is_none() {
+            return Err(format!("Unknown column '{name}' at position {pos}"));
+        }
+        self.columns
+            .numeric_column(&name)
+            .map(Node::Column)
+            .map_err(|err| format!("{err} at position {pos}"))
+    }
+
+    /// Arguments of a call, after its opening parenthesis.
+    fn call(&mut self, function: Function, name: &str, pos: usize) -> Result<Node, String> {
+        let mut args = Vec::new();
+        if !self.tokens.eat(")") {
+            loop {
+                args.push(self.or()?);
+                if self.tokens.eat(")") {
+                    break;
+                }
+                if !self.tokens.eat(",") {
+                    return Err(self.tokens.expected("',' or ')'"));
+                }
+            }
+        }
+
+        let (min, max) = function.arity();
+        if args.len() < min || args.len() > max {
+            let expected = match (min, max) {
+                (min, max) if min == max => format!("{min}"),
+                (min, usize::MAX) => format!("at least {min}"),
+                (min, max) => format!("{min} or {max}"),
+            };
+            return Err(format!(
+                "Function '{name}' at position {pos} takes {expected} arguments, got {}",
+                args.len()
+            ));
+        }
+        Ok(Node::Call(function, args))
+    }
+}
+
+/// Headers, types and formulas of a `mutate`, each formula parsed against
+/// the columns produced by the ones before it.
+///
+/// A formula named after an existing column replaces its values and type,
+/// any other appends a column of the formula's type.
+pub fn compile<S: AsRef<str>>(
+    headers: &mut Vec<String>,
+    types: &mut Vec<ColumnType>,
+    sources: &[S],
+) -> Result<Vec<(usize, Formula)>, String> {
+    struct Table<'a>(&'a [String], &'a [ColumnType]);
+
+    impl Columns for Table<'_> {
+        fn headers(&self) -> &[String] {
+            self.0
+        }
+
+        fn types(&self) -> &[ColumnType] {
+            self.1
+        }
+    }
+
+    let mut formulas = Vec::new();
+    for source in sources {
+        let formula = Formula::parse(source.as_ref(), &Table(headers, types))?;
+        let idx = match headers.iter().position(|header| *header == formula.name) {
+            Some(idx) => idx,
+            None => {
+                headers.push(formula.name.clone());
+                types.push(formula.ty);
+                headers.len() - 1
+            }
+        };
+        types[idx] = formula.ty;
+        formulas.push((idx, formula));
+    }
+    Ok(formulas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::Dataset;
+
+    fn dataset() -> Dataset {
+        let records = [["2", "3.5", "x"], ["4", "", "y"], ["-9", "0", "z"]]
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect();
+        let headers = ["price", "quantity", "name"].map(String::from).to_vec();
+        Dataset::from_records(headers, records).unwrap()
+    }
+
+    fn values(source: &str) -> Vec<Value> {
+        let dataset = dataset();
+        let formula = Formula::parse(source, &dataset).unwrap();
+        dataset
+            .rows
+            .iter()
+            .map(|row| formula.evaluate(row))
+            .collect()
+    }
+
+    fn floats(values: &[Option<f64>]) -> Vec<Value> {
+        values
+            .iter()
+            .map(|value| value.map_or(Value::Null, Value::Float))
+            .collect()
+    }
+
+    #[test]
+    fn test_arithmetic_and_precedence() {
+        assert_eq!(
+            values("total = price * quantity"),
+            floats(&[Some(7.0), None, Some(-0.0)])
+        );
+        assert_eq!(
+            values("t = 1 + price * 2 ^ 2 % 5"),
+            floats(&[Some(4.0), Some(2.0), Some(0.0)])
+        );
+        assert_eq!(
+            values("t = -2 ^ 2 + (1 - price) / 2"),
+            floats(&[Some(-4.5), Some(-5.5), Some(1.0)])
+        );
+        assert_eq!(
+            values("ratio = price / quantity"),
+            floats(&[Some(2.0 / 3.5), None, None])
+        );
+    }
+
+    #[test]
+    fn test_integer_results() {
+        let ints = |values: &[Option<i64>]| {
+            values
+                .iter()
+                .map(|value| value.map_or(Value::Null, Value::Int))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            values("t = -price * 2 % 5 + 1"),
+            ints(&[Some(-3), Some(-2), Some(4)])
+        );
+        assert_eq!(values("t = price % 0"), ints(&[None, None, None]));
+        assert_eq!(
+            values("t = price / 2"),
+            floats(&[Some(1.0), Some(2.0), Some(-4.5)])
+        );
+        assert_eq!(
+            values("t = price + 0.5"),
+            floats(&[Some(2.5), Some(4.5), Some(-8.5)])
+        );
+        let dataset = dataset();
+        assert_eq!(
+            Formula::parse("t = price", &dataset).unwrap().ty,
+            ColumnType::Int
+        );
+        assert_eq!(
+            Formula::parse("t = price ^ 2", &dataset).unwrap().ty,
+            ColumnType::Float
+        );
+    }
+
+    #[test]
+    fn test_functions_and_conditions() {
+        assert_eq!(
+            values("t = round(sqrt(abs(price)) * 10, 1)"),
+            floats(&[Some(14.1), Some(20.0), Some(30.0)])
+        );
+        assert_eq!(
+            values("t = max(price, 0, 1) + min(price, 1)"),
+            floats(&[Some(3.0), Some(5.0), Some(-8.0)])
+        );
+        assert_eq!(
+            values("t = if(price > 0 && !(quantity == 0), floor(quantity), ceil(-0.5))"),
+            floats(&[Some(3.0), None, Some(-0.0)])
+        );
+        let logs = values("t = log(exp(price)) + log(8, 2)");
+        assert!((logs[0].as_f64().unwrap() - 5.0).abs() < 1e-9);
+        assert_eq!(
+            values("t = log(price)"),
+            floats(&[Some(2f64.ln()), Some(4f64.ln()), None])
+        );
+    }
+
+    #[test]
+    fn test_errors_report_positions() {
+        let dataset = dataset();
+        let error = |source| Formula::parse(source, &dataset).unwrap_err();
+
+        assert_eq!(
+            error("total = price * quantiy"),
+            "Unknown column 'quantiy' at position 17"
+        );
+        assert_eq!(
+            error("t = name + 1"),
+            "Column 'name' holds string values, expected numbers at position 5"
+        );
+        assert_eq!(error("t = (price + 1"), "Expected ')' at position 15");
+        assert_eq!(
+            error("t = price * / 2"),
+            "Expected a number, column or function at position 13, found '/'"
+        );
+        assert_eq!(
+            error("t = if(price, 1)"),
+            "Function 'if' at position 5 takes 3 arguments, got 2"
+        );
+        assert_eq!(error("t = sin(1)"), "Unknown function 'sin' at position 5");
+        assert_eq!(
+            error("price * 2"),
+            "Expected NAME = EXPRESSION, got 'price * 2'"
+        );
+        assert_eq!(error("t == 2"), "Expected NAME = EXPRESSION, got 't == 2'");
+    }
+
+    #[test]
+    fn test_compile_appends_and_replaces() {
+        let mut headers = ["price", "quantity"].map(String::from).to_vec();
+        let mut types = vec![ColumnType::Int, ColumnType::Int];
+        let formulas = compile(
+            &mut headers,
+            &mut types,
+            &[
+                "total = price * quantity",
+                "price = total / 2",
+                "Half = total / 2",
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(headers, ["price", "quantity", "total", "half"]);
+        assert_eq!(
+            types,
+            [
+                ColumnType::Float,
+                ColumnType::Int,
+                ColumnType::Int,
+                ColumnType::Float
+            ]
+        );
+        assert_eq!(
+            formulas.iter().map(|(idx, _)| *idx).collect::<Vec<_>>(),
+            [2, 0, 3]
+        );
+    }
+}
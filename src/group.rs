@@ -0,0 +1,304 @@
+use std::{collections::HashMap, str::FromStr};
+
+use clap::ValueEnum;
+
+use crate::{
+    dataset::{Columns, Dataset},
+    stats::{Accumulator, Aggregate},
+    value::{ColumnType, Value},
+};
+
+/// One `--agg` of a group-by, written `AGGREGATE[:COLUMN]`, e.g. `mean:price`.
+/// `count` without a column counts the rows of each group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupAggregate {
+    pub aggregate: Aggregate,
+    pub column: Option<String>,
+}
+
+impl GroupAggregate {
+    /// Header of the output column, e.g. `mean_price`.
+    pub fn name(&self) -> String {
+        let aggregate = self
+            .aggregate
+            .to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default();
+        match &self.column {
+            Some(column) => format!("{aggregate}_{column}"),
+            None => aggregate,
+        }
+    }
+}
+
+impl FromStr for GroupAggregate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, column) = match s.split_once(':') {
+            Some((name, column)) => (name, Some(column.trim().to_lowercase())),
+            None => (s, None),
+        };
+        let aggregate = Aggregate::from_str(name.trim(), true).map_err(|_| {
+            format!("Unknown aggregate '{name}', expected mean, sum, min, max or count")
+        })?;
+
+        if column.is_none() && aggregate != Aggregate::Count {
+            return Err(format!(
+                "Aggregate '{name}' needs a column, e.g. {name}:price"
+            ));
+        }
+        Ok(Self { aggregate, column })
+    }
+}
+
+/// Rows of one distinct key.
+struct Group {
+    key: Vec<Value>,
+    rows: usize,
+    accumulators: Vec<Accumulator>,
+}
+
+/// Aggregate `rows` per distinct value of the `keys` columns.
+///
+/// The result has one row per group, in order of first appearance, holding
+/// the key values followed by one column per aggregate.
+pub fn group_by<I, R, S>(
+    columns: &impl Columns,
+    rows: I,
+    keys: &[S],
+    aggregates: &[GroupAggregate],
+    include_nulls: bool,
+) -> Result<Dataset, String>
+where
+    I: Iterator<Item = Result<R, String>>,
+    R: AsRef<[Value]>,
+    S: AsRef<str>,
+{
+    if keys.is_empty() {
+        return Err("No group keys passed".to_string());
+    }
+    let key_indices = keys
+        .iter()
+        .map(|key| {
+            columns
+                .column_index(key.as_ref())
+                .ok_or_else(|| format!("Invalid category: {}", key.as_ref()))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let agg_indices = aggregates
+        .iter()
+        .map(|agg| match (&agg.column, agg.aggregate) {
+            (None, _) => Ok(None),
+            (Some(column), Aggregate::Count) => columns
+                .column_index(column)
+                .map(Some)
+                .ok_or_else(|| format!("Invalid category: {column}")),
+            (Some(column), _) => columns.numeric_column(column).map(Some),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    // Columns of the result must be unique for later commands to find them.
+    let mut headers: Vec<String> = key_indices
+        .iter()
+        .map(|&idx| columns.headers()[idx].clone())
+        .collect();
+    for agg in aggregates {
+        let name = agg.name();
+        if headers.contains(&name) {
+            return Err(format!(
+                "Aggregate column '{name}' appears twice in the group output"
+            ));
+        }
+        headers.push(name);
+    }
+
+    let mut groups: Vec<Group> = Vec::new();
+    let mut lookup: HashMap<Vec<String>, usize> = HashMap::new();
+
+    for row in rows {
+        let row = row?;
+        let row = row.as_ref();
+
+        let key: Vec<Value> = key_indices.iter().map(|&idx| row[idx].clone()).collect();
+        let lookup_key = key.iter().map(|value| value.to_string()).collect();
+        let idx = *lookup.entry(lookup_key).or_insert_with(|| {
+            groups.push(Group {
+                key,
+                rows: 0,
+                accumulators: vec![Accumulator::default(); aggregates.len()],
+            });
+            groups.len() - 1
+        });
+
+        let group = &mut groups[idx];
+        group.rows += 1;
+        for (acc, column) in group.accumulators.iter_mut().zip(&agg_indices) {
+            if let Some(column) = column {
+                acc.push(&row[*column]);
+            }
+        }
+    }
+
+    let mut types: Vec<ColumnType> = key_indices
+        .iter()
+        .map(|&idx| columns.types()[idx])
+        .collect();
+    types.extend(aggregates.iter().zip(&agg_indices).map(|(agg, column)| {
+        let input = column.map_or(ColumnType::Int, |idx| columns.types()[idx]);
+        agg.aggregate.result_type(input)
+    }));
+
+    let rows = groups
+        .into_iter()
+        .map(|group| {
+            let mut row = group.key;
+            for ((agg, acc), column) in aggregates.iter().zip(&group.accumulators).zip(&agg_indices)
+            {
+                row.push(match column {
+                    Some(_) => acc.finish(agg.aggregate, include_nulls),
+                    None => Value::Int(group.rows as i64),
+                });
+            }
+            row
+        })
+        .collect();
+
+    Ok(Dataset {
+        headers,
+        types,
+        rows,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sales() -> Dataset {
+        let records = [
+            ["north", "a", "10", "1"],
+            ["south", "a", "4", "2"],
+            ["north", "b", "", "3"],
+            ["north", "a", "20", "4"],
+            ["", "b", "7", "5"],
+        ]
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+        .collect();
+        let headers = ["region", "kind", "price", "quantity"]
+            .map(String::from)
+            .to_vec();
+        Dataset::from_records(headers, records).unwrap()
+    }
+
+    fn aggs(specs: &[&str]) -> Vec<GroupAggregate> {
+        specs.iter().map(|spec| spec.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_group_by_one_key() {
+        let sales = sales();
+        let aggregates = aggs(&["mean:price", "count", "max:quantity", "count:price"]);
+        let result = sales.group_by(&["region"], &aggregates, false).unwrap();
+
+        assert_eq!(
+            result.headers,
+            [
+                "region",
+                "mean_price",
+                "count",
+                "max_quantity",
+                "count_price"
+            ]
+        );
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![
+                    Value::Str("north".into()),
+                    Value::Float(15.0),
+                    Value::Int(3),
+                    Value::Int(4),
+                    Value::Int(2)
+                ],
+                vec![
+                    Value::Str("south".into()),
+                    Value::Float(4.0),
+                    Value::Int(1),
+                    Value::Int(2),
+                    Value::Int(1)
+                ],
+                vec![
+                    Value::Null,
+                    Value::Float(7.0),
+                    Value::Int(1),
+                    Value::Int(5),
+                    Value::Int(1)
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_by_key_tuple() {
+        let sales = sales();
+        let result = sales
+            .group_by(&["region", "kind"], &aggs(&["sum:price"]), true)
+            .unwrap();
+
+        let rows: Vec<String> = result
+            .rows
+            .iter()
+            .map(|row| format!("{}/{}={}", row[0], row[1], row[2]))
+            .collect();
+        assert_eq!(rows, ["north/a=30", "south/a=4", "north/b=", "/b=7"]);
+    }
+
+    #[test]
+    fn test_invalid_aggregates() {
+        assert!("median:price".parse::<GroupAggregate>().is_err());
+        assert!("mean".parse::<GroupAggregate>().is_err());
+        assert_eq!(
+            "Count".parse::<GroupAggregate>(),
+            Ok(GroupAggregate {
+                aggregate: Aggregate::Count,
+                column: None
+            })
+        );
+
+        let sales = sales();
+        assert_eq!(
+            sales
+                .group_by(&["region"], &aggs(&["mean:kind"]), false)
+                .unwrap_err(),
+            "Column 'kind' holds string values, expected numbers"
+        );
+        assert!(
+            sales
+                .group_by(&["missing"], &aggs(&["count"]), false)
+                .is_err()
+        );
+
+        let duplicate =
+            |keys: &[&str], specs: &[&str]| sales.group_by(keys, &aggs(specs), false).unwrap_err();
+        assert_eq!(
+            duplicate(&["region"], &["count", "count"]),
+            "Aggregate column 'count' appears twice in the group output"
+        );
+        assert_eq!(
+            duplicate(&["region"], &["mean:price", "MEAN:Price"]),
+            "Aggregate column 'mean_price' appears twice in the group output"
+        );
+        let renamed = sales
+            .select(&["region:count".parse().unwrap()], &[])
+            .unwrap();
+        assert_eq!(
+            renamed
+                .group_by(&["count"], &aggs(&["count"]), false)
+                .unwrap_err(),
+            "Aggregate column 'count' appears twice in the group output"
+        );
+    }
+}
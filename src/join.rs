@@ -0,0 +1,371 @@
+use std::collections::{HashMap, VecDeque};
+
+use clap::ValueEnum;
+
+use crate::{
+    dataset::{Columns, Dataset, RowStream},
+    value::Value,
+};
+
+/// Which rows a join keeps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum JoinKind {
+    /// Pairs of matching rows
+    #[default]
+    Inner,
+    /// Matching pairs, plus left rows without a match
+    Left,
+    /// Matching pairs, plus right rows without a match
+    Right,
+    /// Matching pairs, plus rows of either side without a match
+    Outer,
+    /// Left rows without a match, left columns only
+    Anti,
+    /// Left rows with a match, left columns only
+    Semi,
+}
+
+impl JoinKind {
+    fn keeps_right_columns(self) -> bool {
+        !matches!(self, Self::Anti | Self::Semi)
+    }
+
+    fn keeps_unmatched_right(self) -> bool {
+        matches!(self, Self::Right | Self::Outer)
+    }
+}
+
+/// Join the rows of `left` with the in-memory `right` table on the `on`
+/// columns, which both sides must have.
+///
+/// `right` is loaded into a hash table and `left` is streamed through it, so
+/// rows come in left order, followed by the unmatched right rows of `right`
+/// and `outer` joins. The key columns appear once, and other columns found on
+/// both sides get the `suffixes` appended, repeated until the name is unique.
+/// Null keys never match.
+pub fn join<I, S>(
+    left: RowStream<I>,
+    right: Dataset,
+    on: &[S],
+    how: JoinKind,
+    suffixes: (&str, &str),
+) -> Result<RowStream<impl Iterator<Item = Result<Vec<Value>, String>> + use<I, S>>, String>
+where
+    I: Iterator<Item = Result<Vec<Value>, String>>,
+    S: AsRef<str>,
+{
+    if on.is_empty() {
+        return Err("No join columns passed".to_string());
+    }
+    let mut left_keys = Vec::new();
+    let mut right_keys = Vec::new();
+    for column in on {
+        let column = column.as_ref();
+        left_keys.push(
+            left.column_index(column)
+                .ok_or_else(|| format!("Invalid category: {column}"))?,
+        );
+        right_keys.push(
+            right
+                .column_index(column)
+                .ok_or_else(|| format!("Join column '{column}' is missing from the right table"))?,
+        );
+    }
+    let right_rest: Vec<usize> = (0..right.headers.len())
+        .filter(|idx| !right_keys.contains(idx))
+        .collect();
+
+    let mut headers = left.headers.clone();
+    let mut types = left.types.clone();
+    if how.keeps_right_columns() {
+        let (left_suffix, right_suffix) = suffixes;
+        for &idx in &right_rest {
+            let name = &right.headers[idx];
+            match headers.iter().position(|header| header == name) {
+                Some(clash) if !left_keys.contains(&clash) => {
+                    // Suffixed names must not collide with any other column,
+                    // including right columns still to come.
+                    let mut taken: Vec<String> = headers.clone();
+                    taken.extend(right.headers.iter().filter(|&other| other != name).cloned());
+                    taken.remove(clash);
+                    let left_name = unique_name(name, left_suffix, &taken);
+                    taken.push(left_name.clone());
+                    let right_name = unique_name(name, right_suffix, &taken);
+                    headers[clash] = left_name;
+                    headers.push(right_name);
+                }
+                _ => headers.push(name.clone()),
+            }
+            types.push(right.types[idx]);
+        }
+    }
+
+    let mut index: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+    for (i, row) in right.rows.iter().enumerate() {
+        if let Some(key) = join_key(row, &right_keys) {
+            index.entry(key).or_default().push(i);
+        }
+    }
+
+    let rows = JoinRows {
+        matched: vec![false; right.rows.len()],
+        left: left.rows,
+        left_width: left.headers.len(),
+        right: right.rows,
+        index,
+        left_keys,
+        right_keys,
+        right_rest,
+        how,
+        pending: VecDeque::new(),
+        next_unmatched: 0,
+    };
+    Ok(RowStream {
+        headers,
+        types,
+        rows,
+    })
+}
+
+/// `name` with `suffix` appended, as many times as needed to differ from
+/// every `taken` name.
+fn unique_name(name: &str, suffix: &str, taken: &[String]) -> String {
+    let mut unique = format!("{name}{suffix}");
+    while !suffix.is_empty() && taken.contains(&unique) {
+        unique.push_str(suffix);
+    }
+    unique
+}
+
+/// Lookup key of a row, none when a key cell is null.
+fn join_key(row: &[Value], keys: &[usize]) -> Option<Vec<String>> {
+    keys.iter()
+        .map(|&idx| (!row[idx].is_null()).then(|| row[idx].to_string()))
+        .collect()
+}
+
+/// Left rows probed one at a time against the hashed right rows.
+struct JoinRows<I> {
+    left: I,
+    left_width: usize,
+    right: Vec<Vec<Value>>,
+    index: HashMap<Vec<String>, Vec<usize>>,
+    left_keys: Vec<usize>,
+    right_keys: Vec<usize>,
+    right_rest: Vec<usize>,
+    how: JoinKind,
+    /// Right rows that matched a left row so far.
+    matched: Vec<bool>,
+    /// Joined rows of the current left row not returned yet.
+    pending: VecDeque<Vec<Value>>,
+    /// Next right row to check once the left rows are exhausted.
+    next_unmatched: usize,
+}
+
+impl<I> JoinRows<I> {
+    fn combine(&self, mut row: Vec<Value>, right: Option<usize>) -> Vec<Value> {
+        match right {
+            Some(i) => row.extend(
+                self.right_rest
+                    .iter()
+                    .map(|&idx| self.right[i][idx].clone()),
+            ),
+            None => row.extend(self.right_rest.iter().map(|_| Value::Null)),
+        }
+        row
+    }
+
+    /// A right row without a match, with its keys in the left key columns.
+    fn unmatched_right(&self, i: usize) -> Vec<Value> {
+        let mut row = vec![Value::Null; self.left_width];
+        for (&left, &right) in self.left_keys.iter().zip(&self.right_keys) {
+            row[left] = self.right[i][right].clone();
+        }
+        self.combine(row, Some(i))
+    }
+}
+
+impl<I> Iterator for JoinRows<I>
+where
+    I: Iterator<Item = Result<Vec<Value>, String>>,
+{
+    type Item = Result<Vec<Value>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.pending.pop_front() {
+                return Some(Ok(row));
+            }
+
+            let Some(row) = self.left.next() else {
+                break;
+            };
+            let row = match row {
+                Ok(row) => row,
+                Err(err) => return Some(Err(err)),
+            };
+
+            let matches = join_key(&row, &self.left_keys)
+                .and_then(|key| self.index.get(&key))
+                .cloned()
+                .unwrap_or_default();
+            match self.how {
+                JoinKind::Semi if !matches.is_empty() => return Some(Ok(row)),
+                JoinKind::Anti if matches.is_empty() => return Some(Ok(row)),
+                JoinKind::Semi | JoinKind::Anti => {}
+                JoinKind::Left | JoinKind::Outer if matches.is_empty() => {
+                    return Some(Ok(self.combine(row, None)));
+                }
+                _ => {
+                    for &i in &matches {
+                        self.matched[i] = true;
+                        let joined = self.combine(row.clone(), Some(i));
+                        self.pending.push_back(joined);
+                    }
+                }
+            }
+        }
+
+        if !self.how.keeps_unmatched_right() {
+            return None;
+        }
+        while self.next_unmatched < self.right.len() {
+            let i = self.next_unmatched;
+            self.next_unmatched += 1;
+            if !self.matched[i] {
+                return Some(Ok(self.unmatched_right(i)));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(headers: &[&str], records: &[&[&str]]) -> Dataset {
+        let records = records
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect();
+        Dataset::from_records(headers.iter().map(|h| h.to_string()).collect(), records).unwrap()
+    }
+
+    fn orders() -> Dataset {
+        table(
+            &["id", "customer_id", "name"],
+            &[
+                &["1", "10", "lamp"],
+                &["2", "20", "desk"],
+                &["3", "", "pen"],
+                &["4", "10", "mug"],
+            ],
+        )
+    }
+
+    fn customers() -> Dataset {
+        table(
+            &["customer_id", "name"],
+            &[&["10", "ada"], &["30", "bob"], &["20", "cy"]],
+        )
+    }
+
+    fn joined(how: JoinKind) -> Dataset {
+        let stream = join(
+            orders().into_stream(),
+            customers(),
+            &["customer_id"],
+            how,
+            ("_x", "_y"),
+        );
+        Dataset::from_stream(stream.unwrap()).unwrap()
+    }
+
+    fn cells(dataset: &Dataset) -> Vec<String> {
+        dataset
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| cell.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_join_kinds() {
+        let inner = joined(JoinKind::Inner);
+        assert_eq!(inner.headers, ["id", "customer_id", "name_x", "name_y"]);
+        assert_eq!(
+            cells(&inner),
+            ["1,10,lamp,ada", "2,20,desk,cy", "4,10,mug,ada"]
+        );
+
+        assert_eq!(
+            cells(&joined(JoinKind::Left)),
+            ["1,10,lamp,ada", "2,20,desk,cy", "3,,pen,", "4,10,mug,ada"]
+        );
+        assert_eq!(
+            cells(&joined(JoinKind::Right)),
+            ["1,10,lamp,ada", "2,20,desk,cy", "4,10,mug,ada", ",30,,bob"]
+        );
+        assert_eq!(cells(&joined(JoinKind::Outer)).len(), 5);
+
+        let semi = joined(JoinKind::Semi);
+        assert_eq!(semi.headers, ["id", "customer_id", "name"]);
+        assert_eq!(cells(&semi), ["1,10,lamp", "2,20,desk", "4,10,mug"]);
+        assert_eq!(cells(&joined(JoinKind::Anti)), ["3,,pen"]);
+    }
+
+    #[test]
+    fn test_suffixes_stay_unique() {
+        let left = table(
+            &["id", "name", "name_x", "name_y"],
+            &[&["1", "lamp", "a", "b"]],
+        );
+        let right = table(&["id", "name", "name_y_y"], &[&["1", "ada", "c"]]);
+        let stream = join(
+            left.into_stream(),
+            right,
+            &["id"],
+            JoinKind::Inner,
+            ("_x", "_y"),
+        );
+        let result = Dataset::from_stream(stream.unwrap()).unwrap();
+        assert_eq!(
+            result.headers,
+            [
+                "id",
+                "name_x_x",
+                "name_x",
+                "name_y",
+                "name_y_y_y",
+                "name_y_y"
+            ]
+        );
+        assert_eq!(cells(&result), ["1,lamp,a,b,ada,c"]);
+    }
+
+    #[test]
+    fn test_missing_join_column() {
+        let error = |on| {
+            join(
+                orders().into_stream(),
+                customers(),
+                &[on],
+                JoinKind::Inner,
+                ("_x", "_y"),
+            )
+            .err()
+            .unwrap()
+        };
+        assert_eq!(
+            error("id"),
+            "Join column 'id' is missing from the right table"
+        );
+        assert_eq!(error("missing"), "Invalid category: missing");
+    }
+}
@@ -0,0 +1,26 @@
+//! Csv data manipulation and visualization.
+//!
+//! [`Dataset`] holds a whole csv input in memory, while [`dataset::RowStream`]
+//! processes rows one at a time for inputs larger than memory.
+
+pub mod chart;
+pub mod csv;
+pub mod dataset;
+pub mod expr;
+pub mod extsort;
+pub mod filter;
+pub mod formula;
+pub mod group;
+pub mod join;
+pub mod output;
+pub mod png;
+pub mod report;
+pub mod select;
+pub mod sketch;
+pub mod sort;
+pub mod stats;
+pub mod stream;
+pub mod svg;
+pub mod value;
+
+pub use dataset::Dataset;
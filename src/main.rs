@@ -1,31 +1,100 @@
 use std::{
-    collections::HashMap,
-    fs::File,
-    io::{self, BufReader, BufWriter, Write, stdin},
-    iter::zip,
-    path::PathBuf,
+    env,
+    ffi::{OsStr, OsString},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, IsTerminal, Write, stdin, stdout},
+    path::{Path, PathBuf},
 };
 
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand};
+use terminal_size::{Height, Width};
+
+use mud::{
+    Dataset,
+    chart::{self, Chart, ChartFormat, ChartOptions},
+    csv::Dialect,
+    dataset::{Columns, RowStream},
+    expr::Expr,
+    filter::{Operator, RowFilter},
+    group::GroupAggregate,
+    join::JoinKind,
+    output::{self, CsvSink, NumberFormat, OutputFormat, Precision, TableStyle},
+    png,
+    report::{self, ReportOptions},
+    select::{ColumnPattern, Selection},
+    sketch::{self, ColumnSketches},
+    sort::{NullOrder, SortKey, SortOptions},
+    stats::{self, Aggregate, BinRule, QuantileMethod},
+    stream::{self, TYPE_SAMPLE_ROWS},
+    svg,
+    value::{ColumnType, Value},
+};
 
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    after_help = "Chain commands with `then`, each one working on the rows of the previous one:\n  \
+                  mud data.csv filter price gt 15 then sort quantity -r then head 10\n\
+                  Write `\\then` to pass a plain `then` argument, e.g. filter word in '\\then'"
+)]
 struct Cli {
     /// Filepath to csv file.
     /// If missing, read from stdin
     filepath: Option<PathBuf>,
+    /// Text printed for null cells in table and csv output
+    #[arg(long, global = true, value_name = "MARKER", default_value = "")]
+    null_marker: String,
+    /// Field delimiter, e.g. ';' or 'tab'.
+    /// If missing, it is detected from the first lines
+    #[arg(long, global = true, value_parser = parse_delimiter)]
+    delimiter: Option<char>,
+    /// Quote character
+    #[arg(long, global = true, default_value_t = '"')]
+    quote: char,
+    /// The first line is data, name the columns column1, column2, ...
+    #[arg(long, global = true, action)]
+    no_header: bool,
+    /// Borders of tables printed to the terminal
+    #[arg(long, global = true, value_enum, default_value_t)]
+    style: TableStyle,
+    /// Decimals of floats, N for every column or COLUMN:N for one, repeatable.
+    /// Tables default to 2, csv and json keep every digit
+    #[arg(long, global = true, value_name = "PRECISION")]
+    precision: Vec<Precision>,
+    /// Group the digits of table numbers by thousands, csv and json stay plain
+    #[arg(long, global = true, action)]
+    thousands: bool,
     /// Sub-command to process the data
     #[command(subcommand)]
     command: Command,
 }
 
+/// A command following `then` in a pipeline.
+#[derive(Parser)]
+#[command(no_binary_name = true)]
+struct Stage {
+    #[command(subcommand)]
+    command: Command,
+}
+
 #[derive(Subcommand)]
 enum Command {
-    /// Sort data by category
+    /// Sort data by one or more categories, e.g. sort region:asc price:desc id
     Sort {
-        /// Sort data by Column name
-        #[arg(value_name = "CATEGORY")]
-        category: String,
+        /// Column names, each with an optional :asc or :desc direction.
+        /// Later columns break ties of earlier ones
+        #[arg(value_name = "CATEGORIES", required = true)]
+        keys: Vec<SortKey>,
+
+        /// Where null cells go
+        #[arg(long, value_enum, default_value_t = NullOrder::Last)]
+        nulls: NullOrder,
+
+        /// Order digit runs in text by value, so item2 comes before item10
+        #[arg(long, action)]
+        natural: bool,
 
         /// Output the first (count) lines
         #[arg(short, long)]
@@ -48,9 +117,35 @@ enum Command {
         #[arg(value_name = "OPERATOR")]
         operator: Operator,
 
-        /// Compare against value
+        /// Compare against value, a regular expression for matches or
+        /// comma separated values for in.
+        /// Not used by is-null and not-null
         #[arg(value_name = "VALUE")]
-        argument: f32,
+        argument: Option<String>,
+
+        /// Compare text ignoring case
+        #[arg(short, long, action)]
+        ignore_case: bool,
+
+        /// Output the first (count) lines
+        #[arg(short, long)]
+        count: Option<usize>,
+
+        /// Output the result in reverse order
+        #[arg(short, long, action)]
+        reverse: bool,
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Filter data by a boolean expression,
+    /// e.g. "price > 15 && (quantity < 3 || id == 7)"
+    Where {
+        /// Comparisons of a column with a number (> >= < <= == !=) or
+        /// `COLUMN is null`, combined with && (and), || (or), ! (not) and parentheses
+        #[arg(value_name = "EXPRESSION")]
+        expression: String,
 
         /// Output the first (count) lines
         #[arg(short, long)]
@@ -64,6 +159,86 @@ enum Command {
         #[arg(short, long)]
         output: Option<PathBuf>,
     },
+    /// Aggregate the rows of each distinct key,
+    /// e.g. group region --agg mean:price --agg count
+    Group {
+        /// Columns whose distinct values (or value tuples) form the groups
+        #[arg(value_name = "KEYS", required = true)]
+        keys: Vec<String>,
+
+        /// AGGREGATE[:COLUMN] with mean, sum, min, max or count.
+        /// count without a column counts the rows of a group
+        #[arg(short, long = "agg", value_name = "AGG", default_value = "count")]
+        aggregates: Vec<GroupAggregate>,
+
+        /// Count null cells: a group containing nulls has a null result
+        #[arg(long, action)]
+        include_nulls: bool,
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Join with the rows of another csv file on shared columns,
+    /// e.g. join customers.csv --on customer_id --how left
+    Join {
+        /// Csv file to join with, its dialect is detected from the first lines
+        file: PathBuf,
+
+        /// Column both files have, repeat it to join on several columns
+        #[arg(long, required = true)]
+        on: Vec<String>,
+
+        /// Which rows to keep
+        #[arg(long, value_enum, default_value_t)]
+        how: JoinKind,
+
+        /// Suffixes of the left and right copies of other columns both files have
+        #[arg(long, value_name = "LEFT,RIGHT", value_parser = parse_suffixes, default_value = "_x,_y")]
+        suffixes: (String, String),
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Keep, reorder and rename columns, e.g. select id price:cost 'value*'
+    Select {
+        /// Column names, COLUMN:NEW_NAME renames, globs like value* and
+        /// regular expressions like /^value[0-9]+$/. All columns if missing
+        #[arg(value_name = "COLUMNS")]
+        columns: Vec<Selection>,
+
+        /// Drop the columns matching a name or pattern
+        #[arg(short = 'x', long, value_name = "PATTERN")]
+        exclude: Vec<ColumnPattern>,
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Add or replace columns computed from others,
+    /// e.g. mutate "total = price * quantity" "ratio = value1 / value2"
+    Mutate {
+        /// NAME = EXPRESSION with + - * / % ^, parentheses, comparisons,
+        /// && || !, abs, sqrt, log, exp, round, floor, ceil, min, max and if(cond, a, b).
+        /// Integers combined with + - * % stay integers
+        #[arg(value_name = "FORMULAS", required = true)]
+        formulas: Vec<String>,
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Keep the first rows
+    Head {
+        /// Number of rows to keep
+        #[arg(value_name = "COUNT", default_value_t = 10)]
+        count: usize,
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
     /// Calculate The Mean
     Mean {
         #[arg(value_name = "CATEGORIES")]
@@ -72,6 +247,10 @@ enum Command {
         #[arg(short = 'x', long)]
         exclude: Option<Vec<String>>,
 
+        /// Count null cells: a column containing nulls has a null result
+        #[arg(long, action)]
+        include_nulls: bool,
+
         /// Output filepath
         #[arg(short, long)]
         output: Option<PathBuf>,
@@ -86,6 +265,127 @@ enum Command {
         #[arg(short = 'x', long)]
         exclude: Option<Vec<String>>,
 
+        /// How the median of an even number of values is computed
+        #[arg(long, value_enum, default_value_t)]
+        method: QuantileMethod,
+
+        #[command(flatten)]
+        approx: ApproxArgs,
+
+        /// Count null cells: a column containing nulls has a null result
+        #[arg(long, action)]
+        include_nulls: bool,
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Calculate quantiles between 0 and 1, e.g. quantile 0.9 0.99 latency
+    Quantile {
+        /// Quantiles, followed by the categories to compute them for
+        #[arg(value_name = "QUANTILES> <CATEGORIES", required = true)]
+        values: Vec<String>,
+
+        /// Exclude a Column
+        #[arg(short = 'x', long)]
+        exclude: Option<Vec<String>>,
+
+        /// How a quantile between two values is computed
+        #[arg(long, value_enum, default_value_t)]
+        method: QuantileMethod,
+
+        #[command(flatten)]
+        approx: ApproxArgs,
+
+        /// Count null cells: a column containing nulls has a null result
+        #[arg(long, action)]
+        include_nulls: bool,
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Calculate The Sum
+    Sum {
+        #[arg(value_name = "CATEGORIES")]
+        categories: Option<Vec<String>>,
+
+        /// Exclude a Column
+        #[arg(short = 'x', long)]
+        exclude: Option<Vec<String>>,
+
+        /// Count null cells: a column containing nulls has a null result
+        #[arg(long, action)]
+        include_nulls: bool,
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Calculate The Minimum
+    Min {
+        #[arg(value_name = "CATEGORIES")]
+        categories: Option<Vec<String>>,
+
+        /// Exclude a Column
+        #[arg(short = 'x', long)]
+        exclude: Option<Vec<String>>,
+
+        /// Count null cells: a column containing nulls has a null result
+        #[arg(long, action)]
+        include_nulls: bool,
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Calculate The Maximum
+    Max {
+        #[arg(value_name = "CATEGORIES")]
+        categories: Option<Vec<String>>,
+
+        /// Exclude a Column
+        #[arg(short = 'x', long)]
+        exclude: Option<Vec<String>>,
+
+        /// Count null cells: a column containing nulls has a null result
+        #[arg(long, action)]
+        include_nulls: bool,
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Calculate The number of values
+    Count {
+        #[arg(value_name = "CATEGORIES")]
+        categories: Option<Vec<String>>,
+
+        /// Exclude a Column
+        #[arg(short = 'x', long)]
+        exclude: Option<Vec<String>>,
+
+        /// Count null cells as well
+        #[arg(long, action)]
+        include_nulls: bool,
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Summary statistics of each numeric column:
+    /// count, nulls, min, max, sum, mean, std, var, quartiles, skewness and kurtosis
+    Describe {
+        #[arg(value_name = "CATEGORIES")]
+        categories: Option<Vec<String>>,
+
+        /// Exclude a Column
+        #[arg(short = 'x', long)]
+        exclude: Option<Vec<String>>,
+
         /// Output filepath
         #[arg(short, long)]
         output: Option<PathBuf>,
@@ -97,14 +397,98 @@ enum Command {
         #[arg(short, long)]
         x: String,
 
-        /// The row on the Y axis
+        /// A column on the Y axis, repeat it to plot several series
+        #[arg(short, long, required = true)]
+        y: Vec<String>,
+
+        /// A column on a secondary Y axis labelled on the right, repeatable
+        #[arg(long = "y2", value_name = "Y2")]
+        secondary: Vec<String>,
+
+        #[command(flatten)]
+        plot: ChartArgs,
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Represent the data as a bar chart, one bar per distinct value of a column
+    Bar {
+        /// The column whose values label the bars
+        #[arg(short, long)]
+        x: String,
+
+        /// AGGREGATE[:COLUMN] giving the height of each bar, as in group
+        #[arg(short, long = "agg", value_name = "AGG", default_value = "count")]
+        aggregate: GroupAggregate,
+
+        #[command(flatten)]
+        plot: ChartArgs,
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Represent the spread of a numeric column as a histogram
+    Hist {
+        /// The column to bin
+        column: String,
+
+        /// Number of bins, at most 1000, picked by --rule when missing
+        #[arg(short, long)]
+        bins: Option<usize>,
+
+        /// Rule picking the number of bins
+        #[arg(long, value_enum, default_value_t)]
+        rule: BinRule,
+
+        #[command(flatten)]
+        plot: ChartArgs,
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Represent the data as a scatter plot with a marker per y column
+    Scatter {
+        /// The row on the X axis
+        #[arg(short, long)]
+        x: String,
+
+        /// A column on the Y axis, repeat it to plot several series
+        #[arg(short, long, required = true)]
+        y: Vec<String>,
+
+        #[command(flatten)]
+        plot: ChartArgs,
+
+        /// Output filepath
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Sum up the data in a standalone html page with statistics, histograms and a preview
+    Report {
+        /// Rows shown in the sortable preview table
+        #[arg(long, default_value_t = 100)]
+        rows: usize,
+
+        /// Number of bins of each histogram, at most 1000, picked by --rule when missing
         #[arg(short, long)]
-        y: String,
+        bins: Option<usize>,
+
+        /// Rule picking the number of bins
+        #[arg(long, value_enum, default_value_t)]
+        rule: BinRule,
 
         /// Output filepath
         #[arg(short, long)]
         output: Option<PathBuf>,
     },
+
     /// Represent the data in Json format
     Json {
         /// Output filepath
@@ -113,126 +497,288 @@ enum Command {
     },
 }
 
-#[derive(Debug, Clone, ValueEnum)]
-enum Operator {
-    /// Greater than
-    Gt,
-    /// Greater than or Equal
-    Gte,
-    /// Less than
-    Lt,
-    /// Less than or Equal
-    Lte,
-    /// Equal to
-    Eq,
-    /// Not Equal to
-    Neq,
-}
-
-fn tabulate_data(data: &[Vec<f32>], headers: &[String]) {
-    if !data.is_empty() && headers.len() != data[0].len() {
-        eprintln!(
-            "Header columns count does not match the data columns count: {} -> {}",
-            headers.len(),
-            data[0].len()
-        );
+/// Size, resolution and format of the charts.
+#[derive(Args, Debug, Clone, Default)]
+struct ChartArgs {
+    /// Plot width in characters, the terminal width by default, or image width in pixels
+    #[arg(long)]
+    width: Option<usize>,
+
+    /// Plot height in lines, the terminal height by default, or image height in pixels
+    #[arg(long)]
+    height: Option<usize>,
 
-        return;
+    /// Draw with Braille characters of 2x4 dots each for a finer resolution
+    #[arg(long, action)]
+    braille: bool,
+
+    /// Draw as text, an svg or a png image, by default picked from the output extension
+    #[arg(long, value_enum)]
+    format: Option<ChartFormat>,
+
+    /// Dots per inch of png images, larger values draw bigger text and lines
+    #[arg(long, default_value_t = png::BASE_DPI)]
+    dpi: u32,
+}
+
+impl ChartArgs {
+    /// The format passed, or the one named by the extension of `output`.
+    fn format(&self, output: Option<&PathBuf>) -> ChartFormat {
+        let extension = output
+            .and_then(|file| file.extension())
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match (self.format, extension.as_deref()) {
+            (Some(format), _) => format,
+            (None, Some("svg")) => ChartFormat::Svg,
+            (None, Some("png")) => ChartFormat::Png,
+            (None, _) => ChartFormat::Text,
+        }
     }
 
-    let rows_as_string: Vec<Vec<String>> = data
-        .iter()
-        .map(|row| row.iter().map(|elem| format!("{elem:.2}")).collect())
-        .collect();
+    /// Width and height in pixels of image charts.
+    fn image_size(&self) -> Result<(u32, u32), String> {
+        let pixels = |size: Option<usize>, default: u32| {
+            size.map_or(Ok(default), |size| {
+                u32::try_from(size).map_err(|_| format!("Image size {size} is too large"))
+            })
+        };
+        Ok((
+            pixels(self.width, svg::SVG_WIDTH)?,
+            pixels(self.height, svg::SVG_HEIGHT)?,
+        ))
+    }
 
-    let mut cols_widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
-    for row in &rows_as_string {
-        for (i, elem) in row.iter().enumerate() {
-            cols_widths[i] = cols_widths[i].max(elem.len());
+    /// Options of a chart, sized to fill the terminal and colored when
+    /// printed to one, unless `NO_COLOR` is set. `secondary_axis` leaves
+    /// room for labels on the right.
+    fn options(&self, output: Option<&PathBuf>, secondary_axis: bool) -> ChartOptions {
+        let to_terminal = output.is_none() && stdout().is_terminal();
+        let terminal = to_terminal.then(terminal_size::terminal_size).flatten();
+        let labels = if secondary_axis { 17 } else { 9 };
+        let (width, height) = match terminal {
+            // Leave room for the y axis labels, the title, the x axis with
+            // its labels, a legend and the prompt.
+            Some((Width(columns), Height(rows))) => (
+                usize::from(columns).saturating_sub(labels),
+                usize::from(rows).saturating_sub(5),
+            ),
+            None => (chart::GRAPH_WIDTH, chart::GRAPH_HEIGHT),
+        };
+        let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        ChartOptions {
+            width: self.width.unwrap_or(width),
+            height: self.height.unwrap_or(height),
+            braille: self.braille,
+            color: to_terminal && !no_color,
         }
     }
+}
 
-    let separator = cols_widths
-        .iter()
-        .map(|&w| "=".repeat(w + 2))
-        .collect::<Vec<_>>()
-        .join("*");
-    println!("{separator}");
+/// Options of the one-pass quantile estimate of `median` and `quantile`.
+#[derive(Args, Debug, Clone, Default)]
+struct ApproxArgs {
+    /// Estimate in one pass and bounded memory with a mergeable sketch instead of sorting
+    #[arg(long, action, conflicts_with = "method")]
+    approx: bool,
 
-    let headers_row = headers
-        .iter()
-        .enumerate()
-        .map(|(i, h)| format!(" {:>width$} ", h, width = cols_widths[i]))
-        .collect::<Vec<_>>()
-        .join("*");
+    /// Merge a sketch saved with --save-sketch, e.g. from another file
+    #[arg(long, value_name = "FILE", requires = "approx")]
+    merge_sketch: Vec<PathBuf>,
+
+    /// Save the sketch of each column as json, to merge it later
+    #[arg(long, value_name = "FILE", requires = "approx")]
+    save_sketch: Option<PathBuf>,
+}
+
+/// How `median` and `quantile` compute their values.
+enum Estimate {
+    /// Sort every value and pick the quantile with a method
+    Exact(QuantileMethod),
+    /// Read the quantile off a sketch
+    Approx(ApproxArgs),
+}
+
+impl Estimate {
+    fn new(method: QuantileMethod, approx: ApproxArgs) -> Self {
+        if approx.approx {
+            Self::Approx(approx)
+        } else {
+            Self::Exact(method)
+        }
+    }
+}
 
-    println!("{headers_row}");
-    println!("{separator}");
+fn parse_suffixes(s: &str) -> Result<(String, String), String> {
+    match s.split_once(',') {
+        Some((left, right)) if left != right => Ok((left.to_string(), right.to_string())),
+        _ => Err(format!("Expected two different suffixes, e.g. _x,_y: {s}")),
+    }
+}
 
-    if rows_as_string.is_empty() {
-        println!("EMPTY!");
-        println!("{separator}");
-        return;
+fn parse_delimiter(s: &str) -> Result<char, String> {
+    match s {
+        "tab" | "\\t" => Ok('\t'),
+        _ => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(format!("Delimiter must be a single character: {s}")),
+            }
+        }
     }
+}
 
-    for row in rows_as_string {
-        let row = row
-            .iter()
-            .enumerate()
-            .map(|(i, elem)| format!(" {:>width$} ", elem, width = cols_widths[i]))
-            .collect::<Vec<_>>()
-            .join("*");
-        println!("{row}");
+impl Command {
+    /// Charts and reports render every row at once and end a pipeline.
+    fn is_chart(&self) -> bool {
+        matches!(
+            self,
+            Command::Line { .. }
+                | Command::Bar { .. }
+                | Command::Hist { .. }
+                | Command::Scatter { .. }
+                | Command::Report { .. }
+        )
     }
 
-    println!("{separator}");
+    fn output(&self) -> Option<&PathBuf> {
+        match self {
+            Command::Sort { output, .. }
+            | Command::Filter { output, .. }
+            | Command::Where { output, .. }
+            | Command::Group { output, .. }
+            | Command::Join { output, .. }
+            | Command::Select { output, .. }
+            | Command::Mutate { output, .. }
+            | Command::Head { output, .. }
+            | Command::Mean { output, .. }
+            | Command::Median { output, .. }
+            | Command::Quantile { output, .. }
+            | Command::Sum { output, .. }
+            | Command::Min { output, .. }
+            | Command::Max { output, .. }
+            | Command::Count { output, .. }
+            | Command::Describe { output, .. }
+            | Command::Line { output, .. }
+            | Command::Bar { output, .. }
+            | Command::Hist { output, .. }
+            | Command::Scatter { output, .. }
+            | Command::Report { output, .. }
+            | Command::Json { output } => output.as_ref(),
+        }
+    }
 }
 
-fn find_index<T: Eq>(arr: &[T], elem: &T) -> Option<usize> {
-    arr.iter().position(|item| item == elem)
+/// Bytes buffered up front so the dialect can be sniffed from the first lines.
+const SNIFF_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Separates the commands of a pipeline. Written as `\then`, it is passed to
+/// the command as a plain `then` argument instead.
+const PIPELINE_SEPARATOR: &str = "then";
+
+/// How csv inputs are read, the piped input as well as files named by
+/// commands like `join`.
+#[derive(Debug, Clone, Copy)]
+struct InputOptions {
+    delimiter: Option<char>,
+    quote: char,
+    no_header: bool,
 }
 
-fn dump_to_file(headers: &[String], data: &[Vec<f32>], filepath: PathBuf) -> io::Result<()> {
-    let file = File::options()
-        .write(true)
-        .truncate(true)
-        .create(true)
-        .open(filepath)?;
-    let mut file = BufWriter::new(file);
-    let headers = headers.join(",");
-    writeln!(file, "{headers}")?;
-    let data = data
-        .iter()
-        .map(|row| {
-            row.iter()
-                .map(|elem| elem.to_string())
-                .collect::<Vec<String>>()
-                .join(",")
+impl InputOptions {
+    /// Dialect of `reader`, sniffed from its first lines unless a delimiter
+    /// was passed.
+    fn dialect<R: BufRead>(&self, reader: &mut R) -> Result<Dialect, String> {
+        match self.delimiter {
+            Some(delimiter) => Ok(Dialect {
+                delimiter,
+                quote: self.quote,
+            }),
+            None => Dialect::sniff_reader(reader, self.quote)
+                .map_err(|err| format!("Read input failed: {err}")),
+        }
+    }
+}
+
+/// Rows passed from one pipeline stage to the next.
+type Rows = Box<dyn Iterator<Item = Result<Vec<Value>, String>>>;
+
+/// Parse the command line, splitting it into one command per pipeline stage.
+fn parse_pipeline<I>(args: I) -> (Cli, Vec<Command>)
+where
+    I: IntoIterator<Item = OsString>,
+{
+    let mut segments = Vec::new();
+    let mut current = Vec::new();
+    for arg in args {
+        if arg == PIPELINE_SEPARATOR {
+            segments.push(std::mem::take(&mut current));
+        } else if let Some(escaped) = unescape_separator(&arg) {
+            current.push(escaped.into());
+        } else {
+            current.push(arg);
+        }
+    }
+    segments.push(current);
+
+    let mut segments = segments.into_iter();
+    let cli = Cli::parse_from(segments.next().unwrap_or_default());
+    let stages = segments
+        .map(|segment| {
+            Stage::try_parse_from(segment)
+                .unwrap_or_else(|err| err.exit())
+                .command
         })
-        .collect::<Vec<String>>()
-        .join("\n");
+        .collect();
+    (cli, stages)
+}
 
-    file.write_all(data.as_bytes())?;
-    Ok(())
+/// `arg` without its first backslash when it escapes the pipeline separator,
+/// so `\then` stands for `then` and `\\then` for `\then`.
+fn unescape_separator(arg: &OsStr) -> Option<&str> {
+    let escaped = arg.to_str()?.strip_prefix('\\')?;
+    (escaped.trim_start_matches('\\') == PIPELINE_SEPARATOR).then_some(escaped)
 }
 
 fn output_result(
-    data: &[Vec<f32>],
-    headers: &[String],
+    table: &Dataset,
     output: Option<PathBuf>,
+    fmt: &OutputFormat,
 ) -> Result<(), String> {
     match output {
-        Some(file) => {
-            dump_to_file(headers, data, file).map_err(|err| format!("Save to file failed: {err}"))
-        }
+        Some(file) => output::dump_to_file(table, file, fmt)
+            .map_err(|err| format!("Save to file failed: {err}")),
         None => {
-            tabulate_data(data, headers);
+            println!("{}", output::render_table(table, fmt)?);
             Ok(())
         }
     }
 }
 
-fn apply_count_and_reverse(data: &mut Vec<Vec<f32>>, count: Option<usize>, reverse: bool) {
+/// Like `output_result`, but rows saved to a file are written as they arrive.
+/// The terminal table needs every row up front to size its columns.
+fn output_rows<I>(
+    stream: RowStream<I>,
+    output: Option<PathBuf>,
+    fmt: &OutputFormat,
+) -> Result<(), String>
+where
+    I: Iterator<Item = Result<Vec<Value>, String>>,
+{
+    match output {
+        Some(file) => {
+            let save_err = |err: io::Error| format!("Save to file failed: {err}");
+            let mut sink = CsvSink::create(file, &stream.headers, fmt).map_err(save_err)?;
+            for row in stream.rows {
+                sink.write_row(&row?, fmt).map_err(save_err)?;
+            }
+            sink.finish().map_err(save_err)
+        }
+        None => output_result(&Dataset::from_stream(stream)?, None, fmt),
+    }
+}
+
+fn apply_count_and_reverse(data: &mut Vec<Vec<Value>>, count: Option<usize>, reverse: bool) {
     if reverse {
         data.reverse();
     }
@@ -241,61 +787,83 @@ fn apply_count_and_reverse(data: &mut Vec<Vec<f32>>, count: Option<usize>, rever
     }
 }
 
-fn handle_sort(
-    mut data: Vec<Vec<f32>>,
-    headers: &[String],
-    category: &str,
+/// `reverse` flips the direction of every key, nulls stay where `options` puts them.
+fn handle_sort<I>(
+    stream: RowStream<I>,
+    keys: &[SortKey],
     count: Option<usize>,
     reverse: bool,
-    output: Option<PathBuf>,
-) -> Result<(), String> {
-    let cat_index = find_index(headers, &category.to_lowercase())
-        .ok_or_else(|| "Invalid category".to_string())?;
-
-    if reverse {
-        data.sort_by(|a, b| b[cat_index].total_cmp(&a[cat_index]));
-    } else {
-        data.sort_by(|a, b| a[cat_index].total_cmp(&b[cat_index]));
-    }
-
-    if let Some(count) = count {
-        data.truncate(count);
-    }
-
-    output_result(&data, headers, output)
+    options: SortOptions,
+) -> Result<RowStream<Rows>, String>
+where
+    I: Iterator<Item = Result<Vec<Value>, String>> + 'static,
+{
+    let keys: Vec<SortKey> = keys
+        .iter()
+        .map(|key| SortKey {
+            column: key.column.clone(),
+            descending: key.descending != reverse,
+        })
+        .collect();
+    Ok(stream
+        .sort(&keys, options)?
+        .take(count.unwrap_or(usize::MAX))
+        .boxed())
 }
 
-#[allow(clippy::too_many_arguments)]
-fn handle_filter(
-    data: Vec<Vec<f32>>,
-    headers: &[String],
+fn handle_filter<I>(
+    stream: RowStream<I>,
     category: &str,
     operator: &Operator,
-    argument: f32,
+    argument: Option<&str>,
+    ignore_case: bool,
     count: Option<usize>,
     reverse: bool,
-    output: Option<PathBuf>,
-) -> Result<(), String> {
-    let cat_index = find_index(headers, &category.to_lowercase())
-        .ok_or_else(|| "Invalid category".to_string())?;
-
-    let mut filtered_data: Vec<Vec<f32>> = data
-        .into_iter()
-        .filter(|row| match operator {
-            Operator::Gt => row[cat_index] > argument,
-            Operator::Lt => row[cat_index] < argument,
-            Operator::Eq => (row[cat_index] - argument).abs() < f32::EPSILON,
-            Operator::Neq => (row[cat_index] - argument).abs() > f32::EPSILON,
-            Operator::Gte => row[cat_index] >= argument,
-            Operator::Lte => row[cat_index] <= argument,
-        })
-        .collect();
+) -> Result<RowStream<Rows>, String>
+where
+    I: Iterator<Item = Result<Vec<Value>, String>> + 'static,
+{
+    let filter = RowFilter::new(&stream, category, *operator, argument, ignore_case)?;
+    filter_rows(stream, filter.into(), count, reverse)
+}
 
-    apply_count_and_reverse(&mut filtered_data, count, reverse);
-    output_result(&filtered_data, headers, output)
+/// Filter rows with a boolean expression such as `price > 15 && quantity <= 4`.
+fn handle_where<I>(
+    stream: RowStream<I>,
+    expression: &str,
+    count: Option<usize>,
+    reverse: bool,
+) -> Result<RowStream<Rows>, String>
+where
+    I: Iterator<Item = Result<Vec<Value>, String>> + 'static,
+{
+    let expr = Expr::parse(expression, &stream)?;
+    filter_rows(stream, expr, count, reverse)
 }
 
-fn get_valid_categories(
+/// Rows are checked one at a time. Only `--reverse` needs to hold the
+/// matching rows in memory.
+fn filter_rows<I>(
+    stream: RowStream<I>,
+    expr: Expr,
+    count: Option<usize>,
+    reverse: bool,
+) -> Result<RowStream<Rows>, String>
+where
+    I: Iterator<Item = Result<Vec<Value>, String>> + 'static,
+{
+    let matches = stream.filter(expr);
+
+    if reverse {
+        let mut table = Dataset::from_stream(matches)?;
+        apply_count_and_reverse(&mut table.rows, count, reverse);
+        return Ok(table.into_stream().boxed());
+    }
+
+    Ok(matches.take(count.unwrap_or(usize::MAX)).boxed())
+}
+
+fn get_valid_categories(
     categories: Option<Vec<String>>,
     exclude: Option<Vec<String>>,
     headers: &[String],
@@ -314,191 +882,230 @@ fn get_valid_categories(
         .collect()
 }
 
-fn handle_mean(
-    data: &[Vec<f32>],
-    headers: &[String],
+/// Resolve the columns a numeric aggregate runs over.
+/// Explicitly requested non-numeric columns are rejected, while the
+/// non-numeric columns picked up by default are skipped.
+fn get_numeric_categories(
+    columns: &impl Columns,
     categories: Option<Vec<String>>,
     exclude: Option<Vec<String>>,
-    output: Option<PathBuf>,
-) -> Result<(), String> {
-    let valid_categories = get_valid_categories(categories, exclude, headers);
+) -> Result<Vec<String>, String> {
+    let explicit = categories.as_ref().is_some_and(|cats| !cats.is_empty());
+    let mut valid_categories = get_valid_categories(categories, exclude, columns.headers());
+
+    if explicit {
+        for cat in &valid_categories {
+            columns.numeric_column(cat)?;
+        }
+    } else {
+        valid_categories.retain(|cat| columns.numeric_column(cat).is_ok());
+    }
 
     if valid_categories.is_empty() {
         return Err("No valid categories passed".to_string());
     }
+    Ok(valid_categories)
+}
 
-    let row_count = data.len() as f32;
-    let cat_indices: Vec<usize> = valid_categories
-        .iter()
-        .map(|cat| find_index(headers, cat).unwrap())
-        .collect();
+fn summary_table(headers: Vec<String>, types: Vec<ColumnType>, values: Vec<Value>) -> Dataset {
+    Dataset {
+        types,
+        headers,
+        rows: vec![values],
+    }
+}
 
-    let means: Vec<f32> = cat_indices
+fn handle_aggregate<I>(
+    stream: RowStream<I>,
+    aggregate: Aggregate,
+    categories: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    include_nulls: bool,
+) -> Result<RowStream<Rows>, String>
+where
+    I: Iterator<Item = Result<Vec<Value>, String>>,
+{
+    let valid_categories = get_numeric_categories(&stream, categories, exclude)?;
+    let types = valid_categories
         .iter()
-        .map(|&idx| data.iter().map(|row| row[idx]).sum::<f32>() / row_count)
-        .collect();
-
-    output_result(&[means], &valid_categories, output)
+        .map(|category| {
+            let idx = stream.numeric_column(category)?;
+            Ok(aggregate.result_type(stream.types[idx]))
+        })
+        .collect::<Result<_, String>>()?;
+    let values = stream.aggregate(aggregate, &valid_categories, include_nulls)?;
+    let table = summary_table(valid_categories, types, values);
+    Ok(table.into_stream().boxed())
 }
 
-fn handle_median(
-    data: &mut [Vec<f32>],
-    headers: &[String],
+fn handle_median<I>(
+    stream: RowStream<I>,
     categories: Option<Vec<String>>,
     exclude: Option<Vec<String>>,
-    output: Option<PathBuf>,
-) -> Result<(), String> {
-    let valid_categories = get_valid_categories(categories, exclude, headers);
+    estimate: &Estimate,
+    include_nulls: bool,
+) -> Result<RowStream<Rows>, String>
+where
+    I: Iterator<Item = Result<Vec<Value>, String>>,
+{
+    let valid_categories = get_numeric_categories(&stream, categories, exclude)?;
+    let medians = match estimate {
+        Estimate::Exact(method) => stream.median(&valid_categories, *method, include_nulls)?,
+        Estimate::Approx(approx) => {
+            approx_quantiles(stream, &valid_categories, &[0.5], approx, include_nulls)?.remove(0)
+        }
+    };
+    let types = vec![ColumnType::Float; valid_categories.len()];
+    let table = summary_table(valid_categories, types, medians);
+    Ok(table.into_stream().boxed())
+}
 
-    if valid_categories.is_empty() {
-        return Err("No valid categories passed".to_string());
+/// Split the arguments of `quantile` into the leading quantiles and the categories.
+fn split_quantiles(values: Vec<String>) -> Result<(Vec<f64>, Option<Vec<String>>), String> {
+    let count = values
+        .iter()
+        .take_while(|value| value.parse::<f64>().is_ok())
+        .count();
+    if count == 0 {
+        return Err("Expected at least one quantile, e.g. quantile 0.9 price".to_string());
     }
 
-    let cat_indices: Vec<usize> = valid_categories
-        .iter()
-        .map(|cat| find_index(headers, cat).unwrap())
+    let mut values = values.into_iter();
+    let qs = values
+        .by_ref()
+        .take(count)
+        .map(|q| q.parse().unwrap())
         .collect();
+    let categories: Vec<String> = values.collect();
+    Ok((qs, (!categories.is_empty()).then_some(categories)))
+}
 
-    // Sort data by each column for median calculation
-    for &idx in &cat_indices {
-        data.sort_by(|a, b| a[idx].total_cmp(&b[idx]));
-    }
-
-    let row_count = data.len();
-    let medians: Vec<f32> = if row_count % 2 == 1 {
-        let mid = row_count / 2;
-        cat_indices.iter().map(|&idx| data[mid][idx]).collect()
-    } else {
-        let (lower, upper) = (row_count / 2 - 1, row_count / 2);
-        cat_indices
-            .iter()
-            .map(|&idx| (data[lower][idx] + data[upper][idx]) / 2.0)
-            .collect()
+fn handle_quantile<I>(
+    stream: RowStream<I>,
+    values: Vec<String>,
+    exclude: Option<Vec<String>>,
+    estimate: &Estimate,
+    include_nulls: bool,
+) -> Result<RowStream<Rows>, String>
+where
+    I: Iterator<Item = Result<Vec<Value>, String>>,
+{
+    let (qs, categories) = split_quantiles(values)?;
+    let valid_categories = get_numeric_categories(&stream, categories, exclude)?;
+    let table = match estimate {
+        Estimate::Exact(method) => {
+            stream.quantiles(&valid_categories, &qs, *method, include_nulls)?
+        }
+        Estimate::Approx(approx) => {
+            let values = approx_quantiles(stream, &valid_categories, &qs, approx, include_nulls)?;
+            Dataset::from_quantiles(valid_categories, &qs, values)
+        }
     };
-
-    output_result(&[medians], &valid_categories, output)
+    Ok(table.into_stream().boxed())
 }
 
-fn handle_line_graph(
-    data: Vec<Vec<f32>>,
-    headers: Vec<String>,
-    x: String,
-    y: String,
-    output: Option<PathBuf>,
-) -> Result<(), String> {
-    // count,offset
-    // 0,5
-    // 1,4
-    // 2,3
-    // 3,2
-    // 4,1
-    // 5,0
-    //
-    // (x,y) = (count,offset)
-    //
-    // 5 *
-    // 4 *  *
-    // 3 *     *
-    // 2 *        *
-    // 1 *           *
-    // 0 *              *
-    //   *  *  *  *  *  *
-    //   0  1  2  3  4  5
-
-    if !headers.contains(&x) || !headers.contains(&y) {
-        return Err("Invalid x or y argument".to_string());
-    }
-
-    let (x, y) = (
-        find_index(&headers, &x.to_lowercase()).unwrap(),
-        find_index(&headers, &y.to_lowercase()).unwrap(),
-    );
-    let mut pairs: Vec<(f32, f32)> = data
-        .iter()
-        .map(|row| (row[x], row[y]))
-        .collect::<Vec<(f32, f32)>>();
-    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
-
-    let (min_x, max_x) = pairs
-        .iter()
-        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &(x, _)| {
-            (min.min(x), max.max(x))
-        });
-
-    let (min_y, max_y) = pairs
-        .iter()
-        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &(_, y)| {
-            (min.min(y), max.max(y))
-        });
-
-    const GRAPH_HEIGHT: usize = 15;
-    const GRAPH_WIDTH: usize = 40;
-
-    let mut grid = vec![vec![' '; GRAPH_WIDTH]; GRAPH_HEIGHT];
-
-    for (x_val, y_val) in pairs.into_iter() {
-        let x_pos = if min_x == max_x {
-            GRAPH_WIDTH / 2
-        } else {
-            ((x_val - min_x) / (max_x - min_x) * (GRAPH_WIDTH - 1) as f32) as usize
-        };
-        let y_pos = if min_y == max_y {
-            GRAPH_HEIGHT / 2
-        } else {
-            ((max_y - y_val) / (max_y - min_y) * (GRAPH_HEIGHT - 1) as f32) as usize
-        };
-
-        if x_pos < GRAPH_WIDTH && y_pos < GRAPH_HEIGHT {
-            grid[y_pos][x_pos] = '*';
+/// Sketch the columns, merging and saving sketches as asked, and estimate
+/// the quantiles `qs` of each.
+fn approx_quantiles<I>(
+    stream: RowStream<I>,
+    columns: &[String],
+    qs: &[f64],
+    approx: &ApproxArgs,
+    include_nulls: bool,
+) -> Result<Vec<Vec<Value>>, String>
+where
+    I: Iterator<Item = Result<Vec<Value>, String>>,
+{
+    let mut sketches = stream.sketch(columns)?;
+    for path in &approx.merge_sketch {
+        let saved = sketch::read_sketches(path)?;
+        for (column, sketch) in columns.iter().zip(&mut sketches) {
+            let other = saved.get(column).ok_or_else(|| {
+                format!("Sketch file {} has no column '{column}'", path.display())
+            })?;
+            sketch.merge(other);
         }
     }
 
-    let mut graph = Vec::new();
-    graph.push(format!("y-axis ({}) x-axis ({})", headers[y], headers[x]));
-
-    for (i, row) in grid.into_iter().enumerate() {
-        let y_val = if max_y == min_y {
-            max_y
-        } else {
-            max_y - (i as f32 / (GRAPH_HEIGHT - 1) as f32) * (max_y - min_y)
-        };
-
-        let line = format!("{:>6.1} |{}", y_val, row.into_iter().collect::<String>());
-        graph.push(line);
+    if let Some(path) = &approx.save_sketch {
+        let saved: ColumnSketches = columns.iter().cloned().zip(sketches.clone()).collect();
+        sketch::write_sketches(path, &saved)?;
     }
+    stats::sketch_quantiles(&sketches, qs, include_nulls)
+}
 
-    let x_axis = format!("        {}", "-".repeat(GRAPH_WIDTH + 1));
-    graph.push(x_axis);
-
-    // let x_label_line = format!(
-    //     "       {:>6.1}{:>width$}{:>6.1}",
-    //     min_x,
-    //     "",
-    //     max_x,
-    //     width = GRAPH_WIDTH - 12,
-    // );
-
-    let num_labels = 5;
-    let mut x_label_line = String::from("       ");
+fn handle_join<I>(
+    stream: RowStream<I>,
+    file: &Path,
+    on: &[String],
+    how: JoinKind,
+    (left_suffix, right_suffix): &(String, String),
+    input: &InputOptions,
+) -> Result<RowStream<Rows>, String>
+where
+    I: Iterator<Item = Result<Vec<Value>, String>> + 'static,
+{
+    let file = File::open(file).map_err(|err| format!("File is missing: {err}"))?;
+    let mut reader = BufReader::with_capacity(SNIFF_BUFFER_SIZE, file);
+    let dialect = input.dialect(&mut reader)?;
+    let right = Dataset::from_reader(reader, dialect, input.no_header)?;
+
+    let joined = stream.join(right, on, how, (left_suffix, right_suffix))?;
+    Ok(joined.boxed())
+}
 
-    for i in 0..num_labels {
-        let pos = (GRAPH_WIDTH - 1) * i / (num_labels - 1);
-        let x_val = if min_x == max_x {
-            min_x
-        } else {
-            min_x + (max_x - min_x) * (i as f32 / (num_labels - 1) as f32)
-        };
+fn handle_describe<I>(
+    stream: RowStream<I>,
+    categories: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+) -> Result<RowStream<Rows>, String>
+where
+    I: Iterator<Item = Result<Vec<Value>, String>>,
+{
+    let valid_categories = get_numeric_categories(&stream, categories, exclude)?;
+    let table = stream.describe(&valid_categories)?;
+    Ok(table.into_stream().boxed())
+}
 
-        if i == 0 {
-            x_label_line.push_str(&format!("{x_val:>6.1}"));
-        } else {
-            let spaces_needed = pos - (x_label_line.len() - 7);
-            x_label_line.push_str(&" ".repeat(spaces_needed));
-            x_label_line.push_str(&format!("{x_val:>6.1}"));
+/// Render a chart command over `table` and print it or write it to its output.
+fn handle_chart(table: Dataset, command: Command) -> Result<(), String> {
+    let (plot, output, secondary_axis) = match &command {
+        Command::Line {
+            plot,
+            output,
+            secondary,
+            ..
+        } => (plot.clone(), output.clone(), !secondary.is_empty()),
+        Command::Bar { plot, output, .. }
+        | Command::Hist { plot, output, .. }
+        | Command::Scatter { plot, output, .. } => (plot.clone(), output.clone(), false),
+        _ => unreachable!("not a chart command"),
+    };
+    let chart = match command {
+        Command::Line {
+            x, y, secondary, ..
+        } => Chart::line(&table, &x, &y, &secondary)?,
+        Command::Bar { x, aggregate, .. } => Chart::bar(&table, &x, &aggregate)?,
+        Command::Hist {
+            column, bins, rule, ..
+        } => Chart::histogram(&table, &column, bins, rule)?,
+        Command::Scatter { x, y, .. } => Chart::scatter(&table, &x, &y)?,
+        _ => unreachable!("not a chart command"),
+    };
+    let format = plot.format(output.as_ref());
+    let graph = match format {
+        ChartFormat::Text => chart
+            .to_text(&plot.options(output.as_ref(), secondary_axis))?
+            .into_bytes(),
+        ChartFormat::Svg => {
+            let (width, height) = plot.image_size()?;
+            svg::render(&chart, width, height).into_bytes()
         }
-    }
-
-    graph.push(x_label_line);
+        ChartFormat::Png => {
+            let (width, height) = plot.image_size()?;
+            png::render(&chart, width, height, plot.dpi)?
+        }
+    };
 
     match output {
         Some(file) => {
@@ -508,116 +1115,287 @@ fn handle_line_graph(
                 .truncate(true)
                 .open(file)
                 .map_err(|err| format!("Failed to open output file: {err}"))?;
-            let graph = graph.join("\n");
-            file.write_all(graph.as_bytes())
+            file.write_all(&graph)
                 .map_err(|err| format!("Failed to write results: {err}"))?;
         }
         None => {
-            for line in graph.iter() {
-                println!("{line}");
-            }
+            let mut stdout = stdout().lock();
+            stdout
+                .write_all(&graph)
+                .and_then(|()| match format {
+                    ChartFormat::Png => Ok(()),
+                    _ => writeln!(stdout),
+                })
+                .map_err(|err| format!("Failed to write results: {err}"))?;
         }
     }
 
     Ok(())
 }
 
-fn handle_to_json(
-    data: &[Vec<f32>],
-    headers: &[String],
-    output: Option<PathBuf>,
+/// Write the html report of `table`, titled `title`, to its output or print it.
+fn handle_report(
+    table: &Dataset,
+    title: &str,
+    command: Command,
+    fmt: &OutputFormat,
 ) -> Result<(), String> {
-    let rows: Vec<HashMap<&str, f32>> = data
-        .iter()
-        .map(|row| zip(headers, row).map(|(h, d)| (h.as_str(), *d)).collect())
-        .collect();
-    let json =
-        serde_json::to_string_pretty(&rows).map_err(|err| format!("Serialize json: {err}"))?;
-
+    let Command::Report {
+        rows,
+        bins,
+        rule,
+        output,
+    } = command
+    else {
+        unreachable!("not a report command")
+    };
+    let options = ReportOptions {
+        preview_rows: rows,
+        bins,
+        rule,
+    };
+    let html = report::render(table, title, &options, fmt)?;
     match output {
         Some(file) => {
-            let mut file =
-                File::create(&file).map_err(|err| format!("Open file {file:?}: {err}"))?;
-            file.write_all(json.as_bytes())
-                .map_err(|err| format!("Write json to file: {err}"))?;
+            fs::write(file, html).map_err(|err| format!("Failed to write results: {err}"))
+        }
+        None => {
+            print!("{html}");
+            Ok(())
         }
-        None => println!("{json}"),
     }
-    Ok(())
 }
 
-fn main() -> Result<(), String> {
-    let args = Cli::parse();
-
-    let content = match args.filepath {
-        Some(filepath) => {
-            let file = File::open(filepath).map_err(|err| format!("File is missing: {err}"))?;
-            let file = BufReader::new(file);
-            io::read_to_string(file).map_err(|err| format!("Read from file failed: {err}"))?
-        }
-        None => {
-            let stdin = stdin();
-            io::read_to_string(stdin).map_err(|err| format!("Read from stdin failed: {err}"))?
+fn handle_to_json<I>(
+    stream: RowStream<I>,
+    output: Option<PathBuf>,
+    numbers: &NumberFormat,
+) -> Result<(), String>
+where
+    I: Iterator<Item = Result<Vec<Value>, String>>,
+{
+    match output {
+        Some(file) => {
+            let file = File::create(&file).map_err(|err| format!("Open file {file:?}: {err}"))?;
+            output::write_json(
+                &mut BufWriter::new(file),
+                &stream.headers,
+                stream.rows,
+                numbers,
+            )
         }
-    };
-
-    let headers = content
-        .lines()
-        .next()
-        .ok_or_else(|| "Missing headers".to_string())?
-        .split(",")
-        .map(|s| s.trim().to_lowercase())
-        .collect::<Vec<String>>();
-
-    let mut data = content
-        .lines()
-        .skip(1)
-        .filter(|&line| (!line.is_empty()))
-        .map(|line| {
-            line.split(",")
-                .map(|elem| elem.trim())
-                .map(|elem| {
-                    elem.parse::<f32>()
-                        .unwrap_or_else(|_| elem.parse::<i32>().unwrap_or(-1) as f32)
-                })
-                .collect::<Vec<f32>>()
-        })
-        .collect::<Vec<Vec<f32>>>();
-
-    if !data.is_empty() && headers.len() != data[0].len() {
-        return Err("Mismatch between header count and data columns".to_string());
+        None => output::write_json(
+            &mut BufWriter::new(stdout().lock()),
+            &stream.headers,
+            stream.rows,
+            numbers,
+        ),
     }
+}
 
-    match args.command {
+/// Run a command that produces rows for the next stage or the final output.
+fn run_command(
+    stream: RowStream<Rows>,
+    command: Command,
+    input: &InputOptions,
+) -> Result<RowStream<Rows>, String> {
+    match command {
         Command::Sort {
-            category,
+            keys,
+            nulls,
+            natural,
             count,
             reverse,
-            output,
-        } => handle_sort(data, &headers, &category, count, reverse, output),
+            ..
+        } => handle_sort(
+            stream,
+            &keys,
+            count,
+            reverse,
+            SortOptions { nulls, natural },
+        ),
         Command::Filter {
             category,
             operator,
             argument,
+            ignore_case,
             count,
             reverse,
-            output,
+            ..
         } => handle_filter(
-            data, &headers, &category, &operator, argument, count, reverse, output,
+            stream,
+            &category,
+            &operator,
+            argument.as_deref(),
+            ignore_case,
+            count,
+            reverse,
         ),
+        Command::Where {
+            expression,
+            count,
+            reverse,
+            ..
+        } => handle_where(stream, &expression, count, reverse),
+        Command::Group {
+            keys,
+            aggregates,
+            include_nulls,
+            ..
+        } => {
+            let groups = stream.group_by(&keys, &aggregates, include_nulls)?;
+            Ok(groups.into_stream().boxed())
+        }
+        Command::Join {
+            file,
+            on,
+            how,
+            suffixes,
+            ..
+        } => handle_join(stream, &file, &on, how, &suffixes, input),
+        Command::Select {
+            columns, exclude, ..
+        } => Ok(stream.select(&columns, &exclude)?.boxed()),
+        Command::Mutate { formulas, .. } => Ok(stream.mutate(&formulas)?.boxed()),
+        Command::Head { count, .. } => Ok(stream.take(count).boxed()),
         Command::Mean {
             categories,
             exclude,
-            output,
-        } => handle_mean(&data, &headers, categories, exclude, output),
+            include_nulls,
+            ..
+        } => handle_aggregate(stream, Aggregate::Mean, categories, exclude, include_nulls),
         Command::Median {
             categories,
             exclude,
-            output,
-        } => handle_median(&mut data, &headers, categories, exclude, output),
-        Command::Line { x, y, output } => handle_line_graph(data, headers, x, y, output),
-        Command::Json { output } => handle_to_json(&data, &headers, output),
+            method,
+            approx,
+            include_nulls,
+            ..
+        } => {
+            let estimate = Estimate::new(method, approx);
+            handle_median(stream, categories, exclude, &estimate, include_nulls)
+        }
+        Command::Quantile {
+            values,
+            exclude,
+            method,
+            approx,
+            include_nulls,
+            ..
+        } => {
+            let estimate = Estimate::new(method, approx);
+            handle_quantile(stream, values, exclude, &estimate, include_nulls)
+        }
+        Command::Sum {
+            categories,
+            exclude,
+            include_nulls,
+            ..
+        } => handle_aggregate(stream, Aggregate::Sum, categories, exclude, include_nulls),
+        Command::Min {
+            categories,
+            exclude,
+            include_nulls,
+            ..
+        } => handle_aggregate(stream, Aggregate::Min, categories, exclude, include_nulls),
+        Command::Max {
+            categories,
+            exclude,
+            include_nulls,
+            ..
+        } => handle_aggregate(stream, Aggregate::Max, categories, exclude, include_nulls),
+        Command::Count {
+            categories,
+            exclude,
+            include_nulls,
+            ..
+        } => handle_aggregate(stream, Aggregate::Count, categories, exclude, include_nulls),
+        Command::Describe {
+            categories,
+            exclude,
+            ..
+        } => handle_describe(stream, categories, exclude),
+        Command::Line { .. }
+        | Command::Bar { .. }
+        | Command::Hist { .. }
+        | Command::Scatter { .. }
+        | Command::Report { .. }
+        | Command::Json { .. } => {
+            Err("charts, reports and json can only be the last command of a pipeline".to_string())
+        }
+    }
+}
+
+fn main() -> Result<(), String> {
+    let (args, stages) = parse_pipeline(env::args_os());
+
+    let title = args
+        .filepath
+        .as_ref()
+        .map_or_else(|| "stdin".to_string(), |file| file.display().to_string());
+    let mut reader: Box<dyn BufRead> = match args.filepath {
+        Some(filepath) => {
+            let file = File::open(filepath).map_err(|err| format!("File is missing: {err}"))?;
+            Box::new(BufReader::with_capacity(SNIFF_BUFFER_SIZE, file))
+        }
+        None => Box::new(BufReader::with_capacity(SNIFF_BUFFER_SIZE, stdin())),
+    };
+    let input = InputOptions {
+        delimiter: args.delimiter,
+        quote: args.quote,
+        no_header: args.no_header,
+    };
+    let dialect = input.dialect(&mut reader)?;
+
+    let (headers, records) = stream::open(reader, dialect, input.no_header)?;
+    let fmt = OutputFormat {
+        null_marker: args.null_marker,
+        dialect,
+        style: args.style,
+        numbers: NumberFormat {
+            precision: args.precision,
+            thousands: args.thousands,
+        },
+    };
+
+    let mut commands = vec![args.command];
+    commands.extend(stages);
+    let last = commands.pop().expect("pipeline has a command");
+
+    // Charts hold every point in memory, so type columns from the whole input.
+    let sample_rows = if commands.is_empty() && last.is_chart() {
+        usize::MAX
+    } else {
+        TYPE_SAMPLE_ROWS
+    };
+    let stream = stream::typed(headers, records, sample_rows)?;
+    let (headers, types) = (stream.headers.clone(), stream.types.clone());
+    let unfit = stream.rows.unfit_cells();
+    let mut stream = stream.boxed();
+    for command in commands {
+        if command.output().is_some() {
+            return Err("Only the last command of a pipeline can write --output".to_string());
+        }
+        stream = run_command(stream, command, &input)?;
+    }
+
+    match last {
+        command @ Command::Report { .. } => {
+            handle_report(&Dataset::from_stream(stream)?, &title, command, &fmt)
+        }
+        command if command.is_chart() => handle_chart(Dataset::from_stream(stream)?, command),
+        Command::Json { output } => handle_to_json(stream, output, &fmt.numbers),
+        command => {
+            let output = command.output().cloned();
+            output_rows(run_command(stream, command, &input)?, output, &fmt)
+        }
+    }?;
+
+    // Cells that do not fit the type inferred for their column were read as nulls.
+    for line in unfit.report(&headers, &types) {
+        eprintln!("Warning: {line}");
     }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -626,97 +1404,171 @@ mod tests {
     use std::fs;
     use tempfile::tempdir;
 
-    fn large_dataset() -> (Vec<String>, Vec<Vec<f32>>) {
-        let headers = vec!["score".to_string(), "age".to_string()];
-        let data = (1..=100)
-            .map(|i| vec![i as f32 * 0.5, 20.0 + (i % 50) as f32])
-            .collect();
-        (headers, data)
+    fn float_table(headers: &[&str], data: Vec<Vec<f64>>) -> Dataset {
+        Dataset {
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            types: vec![ColumnType::Float; headers.len()],
+            rows: data
+                .into_iter()
+                .map(|row| row.into_iter().map(Value::Float).collect())
+                .collect(),
+        }
     }
 
-    #[test]
-    fn test_dump_to_file() {
-        let dir = tempdir().unwrap();
-        let file_path = dir.path().join("test.csv");
+    fn plain() -> OutputFormat {
+        OutputFormat::default()
+    }
 
-        let headers = vec!["a".to_string(), "b".to_string()];
-        let data = vec![vec![1.0, 2.0], vec![3.0, 4.0]];
+    fn input() -> InputOptions {
+        InputOptions {
+            delimiter: None,
+            quote: '"',
+            no_header: false,
+        }
+    }
 
-        let result = dump_to_file(&headers, &data, file_path.clone());
-        assert!(result.is_ok());
+    /// Print the rows of a command as the last stage of a pipeline would.
+    fn show(stream: Result<RowStream<Rows>, String>) -> Result<(), String> {
+        output_rows(stream?, None, &plain())
+    }
 
-        let content = fs::read_to_string(file_path).unwrap();
-        assert!(content.contains("a,b"));
-        assert!(content.contains("1,2"));
-        assert!(content.contains("3,4"));
+    fn large_dataset() -> Dataset {
+        let data = (1..=100)
+            .map(|i| vec![i as f64 * 0.5, 20.0 + (i % 50) as f64])
+            .collect();
+        float_table(&["score", "age"], data)
     }
 
     #[test]
     fn test_large_dataset_performance() {
-        let (headers, data) = large_dataset();
+        let table = large_dataset();
 
         // Test that operations complete on larger datasets
         let start = std::time::Instant::now();
-        let result = handle_sort(data.clone(), &headers, "score", None, false, None);
+        let result = show(handle_sort(
+            table.clone().into_stream(),
+            &[SortKey::new("score", false)],
+            None,
+            false,
+            SortOptions::default(),
+        ));
         let duration = start.elapsed();
 
         assert!(result.is_ok());
         assert!(duration.as_millis() < 1000); // Should complete within 1 second
 
-        let result = handle_mean(&data, &headers, None, None, None);
+        let result = show(handle_aggregate(
+            table.clone().into_stream(),
+            Aggregate::Mean,
+            None,
+            None,
+            false,
+        ));
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_edge_case_single_row() {
-        let headers = vec!["value".to_string()];
-        let mut data = vec![vec![42.0]];
+        let table = float_table(&["value"], vec![vec![42.0]]);
 
-        assert!(handle_sort(data.clone(), &headers, "value", None, false, None).is_ok());
         assert!(
-            handle_filter(
-                data.clone(),
-                &headers,
+            show(handle_sort(
+                table.clone().into_stream(),
+                &[SortKey::new("value", false)],
+                None,
+                false,
+                SortOptions::default()
+            ))
+            .is_ok()
+        );
+        assert!(
+            show(handle_filter(
+                table.clone().into_stream(),
                 "value",
                 &Operator::Eq,
-                42.0,
-                None,
+                Some("42.0"),
                 false,
-                None
-            )
+                None,
+                false
+            ))
+            .is_ok()
+        );
+        assert!(
+            show(handle_aggregate(
+                table.clone().into_stream(),
+                Aggregate::Mean,
+                None,
+                None,
+                false
+            ))
+            .is_ok()
+        );
+        assert!(
+            show(handle_median(
+                table.clone().into_stream(),
+                None,
+                None,
+                &Estimate::Exact(QuantileMethod::Linear),
+                false
+            ))
             .is_ok()
         );
-        assert!(handle_mean(&data, &headers, None, None, None).is_ok());
-        assert!(handle_median(&mut data, &headers, None, None, None).is_ok());
     }
 
     #[test]
     fn test_edge_case_negative_values() {
-        let headers = vec!["temp".to_string()];
-        let mut data = vec![vec![-10.5], vec![0.0], vec![-5.2], vec![15.3]];
+        let table = float_table(
+            &["temp"],
+            vec![vec![-10.5], vec![0.0], vec![-5.2], vec![15.3]],
+        );
 
-        assert!(handle_sort(data.clone(), &headers, "temp", None, false, None).is_ok());
         assert!(
-            handle_filter(
-                data.clone(),
-                &headers,
+            show(handle_sort(
+                table.clone().into_stream(),
+                &[SortKey::new("temp", false)],
+                None,
+                false,
+                SortOptions::default()
+            ))
+            .is_ok()
+        );
+        assert!(
+            show(handle_filter(
+                table.clone().into_stream(),
                 "temp",
                 &Operator::Lt,
-                0.0,
-                None,
+                Some("0.0"),
                 false,
-                None
-            )
+                None,
+                false
+            ))
+            .is_ok()
+        );
+        assert!(
+            show(handle_aggregate(
+                table.clone().into_stream(),
+                Aggregate::Mean,
+                None,
+                None,
+                false
+            ))
+            .is_ok()
+        );
+        assert!(
+            show(handle_median(
+                table.clone().into_stream(),
+                None,
+                None,
+                &Estimate::Exact(QuantileMethod::Linear),
+                false
+            ))
             .is_ok()
         );
-        assert!(handle_mean(&data, &headers, None, None, None).is_ok());
-        assert!(handle_median(&mut data, &headers, None, None, None).is_ok());
     }
 
     #[test]
     fn test_all_operators() {
-        let headers = vec!["value".to_string()];
-        let data = vec![vec![10.0], vec![20.0], vec![30.0]];
+        let table = float_table(&["value"], vec![vec![10.0], vec![20.0], vec![30.0]]);
 
         let operators = vec![
             Operator::Gt,
@@ -728,29 +1580,358 @@ mod tests {
         ];
 
         for op in operators {
-            let result = {
-                let data = data.clone();
-                let headers: &[String] = &headers;
-                let operator: &Operator = &op;
-                let argument = 20.0;
-                let cat_index = find_index(headers, &"value".to_lowercase()).unwrap();
-
-                let mut filtered_data: Vec<Vec<f32>> = data
-                    .into_iter()
-                    .filter(|row| match operator {
-                        Operator::Gt => row[cat_index] > argument,
-                        Operator::Lt => row[cat_index] < argument,
-                        Operator::Eq => row[cat_index] == argument,
-                        Operator::Neq => row[cat_index] != argument,
-                        Operator::Gte => row[cat_index] >= argument,
-                        Operator::Lte => row[cat_index] <= argument,
-                    })
-                    .collect();
-
-                apply_count_and_reverse(&mut filtered_data, None, false);
-                output_result(&filtered_data, headers, None)
-            };
+            let result = show(handle_filter(
+                table.clone().into_stream(),
+                "value",
+                &op,
+                Some("20.0"),
+                false,
+                None,
+                false,
+            ));
             assert!(result.is_ok(), "Failed for operator: {op:?}");
         }
     }
+
+    #[test]
+    fn test_numeric_commands_reject_string_columns() {
+        let records = vec![
+            vec!["apple".to_string(), "2".to_string()],
+            vec!["pear".to_string(), "4".to_string()],
+        ];
+        let table =
+            Dataset::from_records(vec!["name".to_string(), "price".to_string()], records).unwrap();
+
+        // Non-numeric columns are skipped by default but rejected when requested.
+        assert!(
+            show(handle_aggregate(
+                table.clone().into_stream(),
+                Aggregate::Mean,
+                None,
+                None,
+                false
+            ))
+            .is_ok()
+        );
+        assert!(
+            show(handle_median(
+                table.clone().into_stream(),
+                Some(vec!["name".to_string()]),
+                None,
+                &Estimate::Exact(QuantileMethod::Linear),
+                false
+            ))
+            .is_err()
+        );
+        assert!(
+            show(handle_filter(
+                table.clone().into_stream(),
+                "name",
+                &Operator::Gt,
+                Some("1.0"),
+                false,
+                None,
+                false
+            ))
+            .is_err()
+        );
+        assert!(
+            show(handle_sort(
+                table.into_stream(),
+                &[SortKey::new("name", false)],
+                None,
+                false,
+                SortOptions::default()
+            ))
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_nulls_are_skipped_unless_included() {
+        let records = vec![
+            vec!["1".to_string(), "".to_string()],
+            vec!["2".to_string(), "-1".to_string()],
+            vec!["3".to_string(), "5".to_string()],
+        ];
+        let table =
+            Dataset::from_records(vec!["id".to_string(), "temp".to_string()], records).unwrap();
+        assert_eq!(table.rows[0][1], Value::Null);
+
+        let run = |agg, include_nulls| table.aggregate(agg, &["temp"], include_nulls).unwrap();
+        assert_eq!(run(Aggregate::Mean, false), vec![Value::Float(2.0)]);
+        assert_eq!(run(Aggregate::Mean, true), vec![Value::Null]);
+        assert_eq!(run(Aggregate::Count, false), vec![Value::Int(2)]);
+        assert_eq!(run(Aggregate::Count, true), vec![Value::Int(3)]);
+        assert_eq!(table.median(&["temp"]).unwrap(), vec![Value::Float(2.0)]);
+
+        for op in [Operator::IsNull, Operator::NotNull] {
+            let result = show(handle_filter(
+                table.clone().into_stream(),
+                "temp",
+                &op,
+                None,
+                false,
+                None,
+                false,
+            ));
+            assert!(result.is_ok(), "Failed for operator: {op:?}");
+        }
+        let result = show(handle_filter(
+            table.into_stream(),
+            "temp",
+            &Operator::Gt,
+            None,
+            false,
+            None,
+            false,
+        ));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_filter_streams_to_file() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("out.csv");
+        let table = float_table(&["v"], (1..=10).map(|i| vec![i as f64]).collect());
+
+        let stream = handle_filter(
+            table.into_stream(),
+            "v",
+            &Operator::Gt,
+            Some("3.0"),
+            false,
+            Some(2),
+            false,
+        );
+        let result = output_rows(stream.unwrap(), Some(file_path.clone()), &plain());
+        assert!(result.is_ok());
+        assert_eq!(fs::read_to_string(file_path).unwrap(), "v\n4\n5\n");
+    }
+
+    #[test]
+    fn test_pipeline_stages() {
+        let args = "mud data.csv filter v gt 3 then sort v -r then head 2 then sum";
+        let (cli, stages) = parse_pipeline(args.split(' ').map(OsString::from));
+        assert_eq!(cli.filepath, Some(PathBuf::from("data.csv")));
+        assert_eq!(stages.len(), 3);
+
+        let table = float_table(&["v"], (1..=10).map(|i| vec![i as f64]).collect());
+        let mut stream = table.into_stream().boxed();
+        for command in std::iter::once(cli.command).chain(stages) {
+            stream = run_command(stream, command, &input()).unwrap();
+        }
+        let result = Dataset::from_stream(stream).unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Float(19.0)]]);
+
+        // An escaped separator is an argument of the command.
+        let args = r"mud data.csv filter word in \then then where \\then";
+        let (cli, stages) = parse_pipeline(args.split(' ').map(OsString::from));
+        let Command::Filter { argument, .. } = cli.command else {
+            panic!("expected a filter");
+        };
+        assert_eq!(argument.as_deref(), Some("then"));
+        let [Command::Where { expression, .. }] = stages.as_slice() else {
+            panic!("expected one where stage");
+        };
+        assert_eq!(expression, r"\then");
+
+        let (cli, _) = parse_pipeline(["mud", "json"].map(OsString::from));
+        let table = float_table(&["v"], vec![]);
+        assert!(run_command(table.into_stream().boxed(), cli.command, &input()).is_err());
+    }
+
+    #[test]
+    fn test_where_expression() {
+        let table = float_table(&["v"], (1..=10).map(|i| vec![i as f64]).collect());
+
+        let stream = handle_where(table.clone().into_stream(), "v < 3 || v >= 9", None, true);
+        let result = Dataset::from_stream(stream.unwrap()).unwrap();
+        assert_eq!(
+            result.rows,
+            [10.0, 9.0, 2.0, 1.0].map(|v| vec![Value::Float(v)])
+        );
+
+        assert!(handle_where(table.into_stream(), "v < 3 || w >= 9", None, false).is_err());
+    }
+
+    #[test]
+    fn test_describe_columns() {
+        let table = float_table(&["a", "b"], (1..=5).map(|i| vec![i as f64, 2.0]).collect());
+
+        let result = handle_describe(table.clone().into_stream(), None, Some(vec!["b".into()]));
+        let result = Dataset::from_stream(result.unwrap()).unwrap();
+        assert_eq!(result.headers, ["statistic", "a"]);
+        assert_eq!(result.rows.len(), 13);
+        assert_eq!(
+            result.rows[9],
+            vec![Value::Str("p50".into()), Value::Float(3.0)]
+        );
+
+        // A constant column has no skewness.
+        let result = handle_describe(table.into_stream(), Some(vec!["b".into()]), None);
+        let result = Dataset::from_stream(result.unwrap()).unwrap();
+        assert_eq!(
+            result.rows[11],
+            vec![Value::Str("skewness".into()), Value::Null]
+        );
+    }
+
+    #[test]
+    fn test_quantile_arguments() {
+        let args = |values: &[&str]| values.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            split_quantiles(args(&["0.9", "0.99", "latency"])),
+            Ok((vec![0.9, 0.99], Some(vec!["latency".to_string()])))
+        );
+        assert_eq!(split_quantiles(args(&["0.5"])), Ok((vec![0.5], None)));
+        assert!(split_quantiles(args(&["latency", "0.9"])).is_err());
+
+        let table = float_table(&["v"], (1..=10).map(|i| vec![i as f64]).collect());
+        let stream = handle_quantile(
+            table.into_stream(),
+            args(&["0.5", "1"]),
+            None,
+            &Estimate::Exact(QuantileMethod::Higher),
+            false,
+        );
+        let result = Dataset::from_stream(stream.unwrap()).unwrap();
+        assert_eq!(result.headers, ["quantile", "v"]);
+        assert_eq!(
+            result.rows,
+            [[0.5, 6.0], [1.0, 10.0]].map(|row| row.map(Value::Float).to_vec())
+        );
+    }
+
+    #[test]
+    fn test_approx_median_merges_saved_sketches() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("sketch.json");
+        let table = |range: std::ops::Range<i32>| {
+            float_table(&["v"], range.map(|i| vec![i as f64]).collect()).into_stream()
+        };
+        let median = |stream, approx| {
+            let stream = handle_median(stream, None, None, &Estimate::Approx(approx), false);
+            Dataset::from_stream(stream.unwrap()).unwrap().rows
+        };
+
+        let save = ApproxArgs {
+            approx: true,
+            save_sketch: Some(path.clone()),
+            ..Default::default()
+        };
+        assert_eq!(median(table(0..5), save), vec![vec![Value::Float(2.0)]]);
+
+        let merge = ApproxArgs {
+            approx: true,
+            merge_sketch: vec![path.clone()],
+            ..Default::default()
+        };
+        assert_eq!(
+            median(table(5..10), merge.clone()),
+            vec![vec![Value::Float(4.5)]]
+        );
+
+        let other = float_table(&["w"], vec![vec![1.0]]).into_stream();
+        let err = handle_median(other, None, None, &Estimate::Approx(merge), false).err();
+        assert_eq!(
+            err,
+            Some(format!("Sketch file {} has no column 'w'", path.display()))
+        );
+    }
+
+    #[test]
+    fn test_join_reads_right_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("rates.csv");
+        fs::write(&path, "v;rate\n1;0.5\n3;1.5\n").unwrap();
+
+        let table = float_table(&["v"], (1..=4).map(|i| vec![i as f64]).collect());
+        let suffixes = ("_x".to_string(), "_y".to_string());
+        let on = ["v".to_string()];
+        let stream = handle_join(
+            table.into_stream(),
+            &path,
+            &on,
+            JoinKind::Left,
+            &suffixes,
+            &input(),
+        );
+        let result = Dataset::from_stream(stream.unwrap()).unwrap();
+
+        assert_eq!(result.headers, ["v", "rate"]);
+        let rates: Vec<Value> = result.rows.into_iter().map(|row| row[1].clone()).collect();
+        assert_eq!(
+            rates,
+            [
+                Value::Float(0.5),
+                Value::Null,
+                Value::Float(1.5),
+                Value::Null
+            ]
+        );
+
+        // The global --quote and --no-header apply to the right file too.
+        fs::write(&path, "1;'a;b'\n3;'c'\n").unwrap();
+        let input = InputOptions {
+            quote: '\'',
+            no_header: true,
+            ..input()
+        };
+        let table = float_table(&["column1"], vec![vec![1.0], vec![3.0]]);
+        let on = ["column1".to_string()];
+        let stream = handle_join(
+            table.into_stream(),
+            &path,
+            &on,
+            JoinKind::Inner,
+            &suffixes,
+            &input,
+        );
+        let result = Dataset::from_stream(stream.unwrap()).unwrap();
+        assert_eq!(result.headers, ["column1", "column2"]);
+        assert_eq!(
+            result.rows,
+            [
+                vec![Value::Float(1.0), Value::Str("a;b".into())],
+                vec![Value::Float(3.0), Value::Str("c".into())],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chart_format_from_extension() {
+        let plot = ChartArgs::default();
+        let format = |file: &str| plot.format(Some(&PathBuf::from(file)));
+        assert_eq!(format("chart.png"), ChartFormat::Png);
+        assert_eq!(format("chart.SVG"), ChartFormat::Svg);
+        assert_eq!(format("chart.txt"), ChartFormat::Text);
+        assert_eq!(plot.format(None), ChartFormat::Text);
+
+        let plot = ChartArgs {
+            format: Some(ChartFormat::Svg),
+            width: Some(300),
+            ..Default::default()
+        };
+        assert_eq!(
+            plot.format(Some(&PathBuf::from("chart.png"))),
+            ChartFormat::Svg
+        );
+        assert_eq!(plot.image_size().unwrap(), (300, svg::SVG_HEIGHT));
+    }
+
+    #[test]
+    fn test_integer_aggregates_print_whole() {
+        let records = (1..=3)
+            .map(|i| vec![(i * 1000).to_string(), format!("{i}.5")])
+            .collect();
+        let table = Dataset::from_records(vec!["id".into(), "x".into()], records).unwrap();
+        let categories = Some(vec!["id".to_string(), "x".to_string()]);
+        let stream = handle_aggregate(table.into_stream(), Aggregate::Max, categories, None, false);
+        let result = Dataset::from_stream(stream.unwrap()).unwrap();
+
+        assert_eq!(result.types, [ColumnType::Int, ColumnType::Float]);
+        let printed = output::render_table(&result, &plain()).unwrap();
+        assert!(printed.contains(" 3000 * 3.50 "), "{printed}");
+    }
 }
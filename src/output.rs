@@ -0,0 +1,693 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    iter::zip,
+    path::PathBuf,
+    str::FromStr,
+};
+
+use clap::ValueEnum;
+use serde::{Serialize, Serializer, ser::SerializeMap};
+
+use crate::{csv::Dialect, csv::write_record, dataset::Dataset, value::Value};
+
+/// Decimals of floats in text tables unless `--precision` says otherwise.
+pub const TABLE_DECIMALS: usize = 2;
+
+/// Magnitudes beyond which floats are written in scientific notation, the
+/// lower one when no precision is set.
+const SCIENTIFIC_ABOVE: f64 = 1e15;
+const SCIENTIFIC_BELOW: f64 = 1e-6;
+
+/// How cells are rendered in table, csv and json output.
+#[derive(Debug, Clone, Default)]
+pub struct OutputFormat {
+    pub null_marker: String,
+    pub dialect: Dialect,
+    pub style: TableStyle,
+    pub numbers: NumberFormat,
+}
+
+/// How numbers are written.
+///
+/// Floats get the decimals of their column, or every significant digit
+/// when none is set, and switch to scientific notation when too large or
+/// too small to show with them. Integers are written whole.
+#[derive(Debug, Clone, Default)]
+pub struct NumberFormat {
+    pub precision: Vec<Precision>,
+    /// Group the digits of the integer part by thousands, in tables only.
+    pub thousands: bool,
+}
+
+/// Decimals of floats: `N` for every column, or `COLUMN:N` for one column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Precision {
+    pub column: Option<String>,
+    pub decimals: usize,
+}
+
+impl FromStr for Precision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (column, decimals) = match s.rsplit_once(':') {
+            Some((column, decimals)) => (Some(column.trim().to_lowercase()), decimals),
+            None => (None, s),
+        };
+        if column.as_ref().is_some_and(|column| column.is_empty()) {
+            return Err(format!("Missing column in precision '{s}'"));
+        }
+        let decimals = decimals
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid precision '{s}', expected N or COLUMN:N"))?;
+        Ok(Self { column, decimals })
+    }
+}
+
+impl NumberFormat {
+    /// Decimals of the floats of column `header`: its own precision, else
+    /// the one of every column.
+    pub fn decimals(&self, header: &str) -> Option<usize> {
+        let column = self
+            .precision
+            .iter()
+            .rfind(|precision| precision.column.as_deref() == Some(header));
+        let all = || {
+            self.precision
+                .iter()
+                .rfind(|precision| precision.column.is_none())
+        };
+        column.or_else(all).map(|precision| precision.decimals)
+    }
+
+    /// `number` with `decimals` decimals, or as many as it needs.
+    pub fn float(&self, number: f64, decimals: Option<usize>) -> String {
+        let (text, scientific) = float_text(number, decimals);
+        if scientific { text } else { self.group(text) }
+    }
+
+    pub fn integer(&self, number: i64) -> String {
+        self.group(number.to_string())
+    }
+
+    /// A plain `number` with its integer digits grouped by thousands if asked.
+    fn group(&self, number: String) -> String {
+        if !self.thousands {
+            return number;
+        }
+        let (sign, unsigned) = match number.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", number.as_str()),
+        };
+        let (digits, fraction) = match unsigned.find('.') {
+            Some(dot) => unsigned.split_at(dot),
+            None => (unsigned, ""),
+        };
+        let mut grouped = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                grouped.push(',');
+            }
+            grouped.push(digit);
+        }
+        format!("{sign}{grouped}{fraction}")
+    }
+
+    /// `value` rounded as tables and csv show it, for json which has no
+    /// digit grouping.
+    fn round(&self, value: &Value, decimals: Option<usize>) -> Value {
+        match value {
+            Value::Float(x) if x.is_finite() && decimals.is_some() => {
+                let (text, _) = float_text(*x, decimals);
+                Value::Float(text.parse().unwrap_or(*x))
+            }
+            _ => value.clone(),
+        }
+    }
+}
+
+/// `number` with `decimals` decimals, or as many as it needs, and whether it
+/// is in scientific notation because its magnitude is too large, or so small
+/// it would round to zero.
+fn float_text(number: f64, decimals: Option<usize>) -> (String, bool) {
+    // Adding zero turns -0 into 0.
+    let number = number + 0.0;
+    let magnitude = number.abs();
+    let smallest = decimals.map_or(SCIENTIFIC_BELOW, |decimals| {
+        0.5 * 10f64.powi(-(decimals as i32))
+    });
+    let scientific = number.is_finite()
+        && (magnitude >= SCIENTIFIC_ABOVE || (magnitude > 0.0 && magnitude < smallest));
+    let text = match (scientific, decimals) {
+        (true, Some(decimals)) => format!("{number:.decimals$e}"),
+        (true, None) => format!("{number:e}"),
+        (false, Some(decimals)) => format!("{number:.decimals$}"),
+        (false, None) => number.to_string(),
+    };
+    (text, scientific)
+}
+
+/// Borders drawn around and between the cells of a text table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TableStyle {
+    /// `=` rules and `*` joints, every cell aligned right
+    #[default]
+    Classic,
+    /// Markdown pipe table with alignment colons
+    Markdown,
+    /// Same as `markdown`, the pipe table GitHub renders
+    Github,
+    /// Unicode box-drawing borders
+    Box,
+    /// `+`, `-` and `|` borders
+    Ascii,
+    /// Columns separated by spaces, without borders
+    Plain,
+    /// Like the psql client
+    Psql,
+    /// reStructuredText simple table
+    Rst,
+}
+
+/// A horizontal rule: its left end, the fill under each column, the joint
+/// between columns and its right end.
+struct Rule(&'static str, &'static str, &'static str, &'static str);
+
+/// How a [`TableStyle`] lays out its lines.
+struct Frame {
+    /// Text before the first cell, between cells and after the last one.
+    edges: [&'static str; 3],
+    /// Spaces the edges add around each cell, which rules span too.
+    padding: usize,
+    top: Option<Rule>,
+    header: Option<Rule>,
+    bottom: Option<Rule>,
+}
+
+impl TableStyle {
+    fn frame(self) -> Frame {
+        let pipes = ["| ", " | ", " |"];
+        match self {
+            Self::Classic => Frame {
+                edges: [" ", " * ", " "],
+                padding: 2,
+                top: Some(Rule("", "=", "*", "")),
+                header: Some(Rule("", "=", "*", "")),
+                bottom: Some(Rule("", "=", "*", "")),
+            },
+            // The markdown header rule is drawn by `alignment_rule`.
+            Self::Markdown | Self::Github => Frame {
+                edges: pipes,
+                padding: 2,
+                top: None,
+                header: None,
+                bottom: None,
+            },
+            Self::Box => Frame {
+                edges: ["│ ", " │ ", " │"],
+                padding: 2,
+                top: Some(Rule("┌", "─", "┬", "┐")),
+                header: Some(Rule("├", "─", "┼", "┤")),
+                bottom: Some(Rule("└", "─", "┴", "┘")),
+            },
+            Self::Ascii => Frame {
+                edges: pipes,
+                padding: 2,
+                top: Some(Rule("+", "-", "+", "+")),
+                header: Some(Rule("+", "=", "+", "+")),
+                bottom: Some(Rule("+", "-", "+", "+")),
+            },
+            Self::Plain => Frame {
+                edges: ["", "  ", ""],
+                padding: 0,
+                top: None,
+                header: None,
+                bottom: None,
+            },
+            Self::Psql => Frame {
+                edges: [" ", " | ", " "],
+                padding: 2,
+                top: None,
+                header: Some(Rule("", "-", "+", "")),
+                bottom: None,
+            },
+            Self::Rst => Frame {
+                edges: ["", "  ", ""],
+                padding: 0,
+                top: Some(Rule("", "=", "  ", "")),
+                header: Some(Rule("", "=", "  ", "")),
+                bottom: Some(Rule("", "=", "  ", "")),
+            },
+        }
+    }
+}
+
+impl OutputFormat {
+    /// A cell as csv output writes it, with floats given `decimals` decimals,
+    /// or as many as they need. Digits are never grouped, so the file reads
+    /// back as numbers.
+    pub fn render(&self, value: &Value, decimals: Option<usize>) -> String {
+        match value {
+            Value::Null => self.null_marker.clone(),
+            Value::Float(x) => float_text(*x, decimals).0,
+            _ => value.to_string(),
+        }
+    }
+
+    /// A cell as text tables show it, like [`OutputFormat::render`] but with
+    /// digits grouped by thousands if asked.
+    pub fn render_cell(&self, value: &Value, decimals: Option<usize>) -> String {
+        match value {
+            Value::Int(i) => self.numbers.integer(*i),
+            Value::Float(x) => self.numbers.float(*x, decimals),
+            _ => self.render(value, decimals),
+        }
+    }
+
+    /// Decimals of the floats of column `header` in text tables.
+    pub fn table_decimals(&self, header: &str) -> usize {
+        self.numbers.decimals(header).unwrap_or(TABLE_DECIMALS)
+    }
+}
+
+/// Render a dataset as a text table for the terminal, in the style of `fmt`.
+///
+/// Text columns are aligned left and numeric ones right, except in the
+/// classic style which aligns every cell right.
+pub fn render_table(dataset: &Dataset, fmt: &OutputFormat) -> Result<String, String> {
+    let (data, headers) = (&dataset.rows, &dataset.headers);
+    if !data.is_empty() && headers.len() != data[0].len() {
+        return Err(format!(
+            "Header columns count does not match the data columns count: {} -> {}",
+            headers.len(),
+            data[0].len()
+        ));
+    }
+
+    let style = fmt.style;
+    let cell = |text: String| match style {
+        TableStyle::Markdown | TableStyle::Github => text.replace('|', "\\|"),
+        _ => text,
+    };
+    let decimals: Vec<usize> = headers
+        .iter()
+        .map(|header| fmt.table_decimals(header))
+        .collect();
+    let headers: Vec<String> = headers.iter().cloned().map(cell).collect();
+    let rows_as_string: Vec<Vec<String>> = data
+        .iter()
+        .map(|row| {
+            zip(row, &decimals)
+                .map(|(elem, &decimals)| cell(fmt.render_cell(elem, Some(decimals))))
+                .collect()
+        })
+        .collect();
+
+    let mut cols_widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in &rows_as_string {
+        for (i, elem) in row.iter().enumerate() {
+            cols_widths[i] = cols_widths[i].max(elem.chars().count());
+        }
+    }
+    let right_aligned: Vec<bool> = (0..headers.len())
+        .map(|i| {
+            style == TableStyle::Classic || dataset.types.get(i).is_some_and(|ty| ty.is_numeric())
+        })
+        .collect();
+
+    let frame = style.frame();
+    let rule = |rule: &Rule| {
+        let Rule(left, fill, join, right) = rule;
+        let fills: Vec<String> = cols_widths
+            .iter()
+            .map(|&w| fill.repeat(w + frame.padding))
+            .collect();
+        format!("{left}{}{right}", fills.join(join))
+    };
+    let line = |cells: &[String]| {
+        let [left, join, right] = frame.edges;
+        let cells: Vec<String> = cells
+            .iter()
+            .enumerate()
+            .map(|(i, elem)| {
+                if right_aligned[i] {
+                    format!("{:>width$}", elem, width = cols_widths[i])
+                } else {
+                    format!("{:<width$}", elem, width = cols_widths[i])
+                }
+            })
+            .collect();
+        let line = format!("{left}{}{right}", cells.join(join));
+        // Borderless styles would end in the padding of the last column.
+        match style {
+            TableStyle::Classic => line,
+            _ => line.trim_end().to_string(),
+        }
+    };
+
+    let mut lines = Vec::new();
+    lines.extend(frame.top.as_ref().map(rule));
+    lines.push(line(&headers));
+    match style {
+        TableStyle::Markdown | TableStyle::Github => {
+            lines.push(alignment_rule(&cols_widths, &right_aligned))
+        }
+        _ => lines.extend(frame.header.as_ref().map(rule)),
+    }
+
+    if rows_as_string.is_empty() && style == TableStyle::Classic {
+        lines.push("EMPTY!".to_string());
+    }
+    for row in &rows_as_string {
+        lines.push(line(row));
+    }
+
+    lines.extend(frame.bottom.as_ref().map(rule));
+    if style == TableStyle::Psql {
+        let count = rows_as_string.len();
+        lines.push(format!(
+            "({count} {})",
+            if count == 1 { "row" } else { "rows" }
+        ));
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Markdown header rule, with a colon on the side each column is aligned to.
+fn alignment_rule(widths: &[usize], right_aligned: &[bool]) -> String {
+    let cells: Vec<String> = zip(widths, right_aligned)
+        .map(|(&width, &right)| {
+            if right {
+                format!("{}:", "-".repeat(width + 1))
+            } else {
+                format!(":{}", "-".repeat(width + 1))
+            }
+        })
+        .collect();
+    format!("|{}|", cells.join("|"))
+}
+
+/// Csv output written one row at a time.
+pub struct CsvSink {
+    file: BufWriter<File>,
+    /// Decimals of the floats of each column.
+    decimals: Vec<Option<usize>>,
+}
+
+impl CsvSink {
+    pub fn create(filepath: PathBuf, headers: &[String], fmt: &OutputFormat) -> io::Result<Self> {
+        let file = File::options()
+            .write(true)
+            .truncate(true)
+            .create(true)
+            .open(filepath)?;
+        let mut file = BufWriter::new(file);
+        write_record(&mut file, headers, &fmt.dialect)?;
+        let decimals = headers
+            .iter()
+            .map(|header| fmt.numbers.decimals(header))
+            .collect();
+        Ok(Self { file, decimals })
+    }
+
+    pub fn write_row(&mut self, row: &[Value], fmt: &OutputFormat) -> io::Result<()> {
+        write_record(
+            &mut self.file,
+            zip(row, &self.decimals).map(|(elem, &decimals)| fmt.render(elem, decimals)),
+            &fmt.dialect,
+        )
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+pub fn dump_to_file(dataset: &Dataset, filepath: PathBuf, fmt: &OutputFormat) -> io::Result<()> {
+    let mut sink = CsvSink::create(filepath, &dataset.headers, fmt)?;
+    for row in &dataset.rows {
+        sink.write_row(row, fmt)?;
+    }
+    sink.finish()
+}
+
+/// A row serialized as a json object, keeping the header order.
+struct JsonRow<'a> {
+    headers: &'a [String],
+    row: &'a [Value],
+    numbers: &'a NumberFormat,
+    decimals: &'a [Option<usize>],
+}
+
+impl Serialize for JsonRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.headers.len()))?;
+        for ((header, value), &decimals) in zip(zip(self.headers, self.row), self.decimals) {
+            map.serialize_entry(header, &self.numbers.round(value, decimals))?;
+        }
+        map.end()
+    }
+}
+
+/// Write rows as a pretty-printed json array, one row at a time, with floats
+/// rounded to the precision of `numbers`.
+pub fn write_json<W, I, R>(
+    writer: &mut W,
+    headers: &[String],
+    rows: I,
+    numbers: &NumberFormat,
+) -> Result<(), String>
+where
+    W: Write,
+    I: Iterator<Item = Result<R, String>>,
+    R: AsRef<[Value]>,
+{
+    let write_err = |err: io::Error| format!("Write json: {err}");
+
+    writer.write_all(b"[").map_err(write_err)?;
+    let decimals: Vec<Option<usize>> = headers
+        .iter()
+        .map(|header| numbers.decimals(header))
+        .collect();
+    let mut empty = true;
+    for row in rows {
+        let row = row?;
+        let json = serde_json::to_string_pretty(&JsonRow {
+            headers,
+            row: row.as_ref(),
+            numbers,
+            decimals: &decimals,
+        })
+        .map_err(|err| format!("Serialize json: {err}"))?;
+
+        let separator = if empty { "\n" } else { ",\n" };
+        writer.write_all(separator.as_bytes()).map_err(write_err)?;
+        let indented = json
+            .lines()
+            .map(|line| format!("  {line}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        writer.write_all(indented.as_bytes()).map_err(write_err)?;
+        empty = false;
+    }
+
+    let end = if empty { "]\n" } else { "\n]\n" };
+    writer.write_all(end.as_bytes()).map_err(write_err)?;
+    writer.flush().map_err(write_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::value::ColumnType;
+
+    fn float_dataset(headers: &[&str], data: Vec<Vec<f64>>) -> Dataset {
+        Dataset {
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            types: vec![ColumnType::Float; headers.len()],
+            rows: data
+                .into_iter()
+                .map(|row| row.into_iter().map(Value::Float).collect())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_dump_to_file() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.csv");
+
+        let dataset = float_dataset(&["a", "b"], vec![vec![1.0, 2.0], vec![3.0, 4.0]]);
+
+        let result = dump_to_file(&dataset, file_path.clone(), &OutputFormat::default());
+        assert!(result.is_ok());
+
+        let content = fs::read_to_string(file_path).unwrap();
+        assert!(content.contains("a,b"));
+        assert!(content.contains("1,2"));
+        assert!(content.contains("3,4"));
+    }
+
+    #[test]
+    fn test_dump_to_file_quotes_strings() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.csv");
+
+        let records = vec![vec!["Smith, John".to_string(), "3".to_string()]];
+        let dataset =
+            Dataset::from_records(vec!["name".to_string(), "id".to_string()], records).unwrap();
+        dump_to_file(&dataset, file_path.clone(), &OutputFormat::default()).unwrap();
+
+        let content = fs::read_to_string(file_path).unwrap();
+        assert_eq!(content, "name,id\n\"Smith, John\",3\n");
+    }
+
+    #[test]
+    fn test_render_table() {
+        let dataset = float_dataset(&["value"], vec![vec![1.5]]);
+        let table = render_table(&dataset, &OutputFormat::default()).unwrap();
+        assert_eq!(table, "=======\n value \n=======\n  1.50 \n=======");
+    }
+
+    #[test]
+    fn test_table_styles() {
+        let records = vec![vec!["a|b".to_string(), "2.5".to_string()]];
+        let dataset =
+            Dataset::from_records(vec!["name".to_string(), "x".to_string()], records).unwrap();
+        let table = |style| {
+            let fmt = OutputFormat {
+                style,
+                ..Default::default()
+            };
+            render_table(&dataset, &fmt).unwrap()
+        };
+
+        assert_eq!(
+            table(TableStyle::Markdown),
+            "| name |    x |\n|:-----|-----:|\n| a\\|b | 2.50 |"
+        );
+        assert_eq!(table(TableStyle::Github), table(TableStyle::Markdown));
+        assert_eq!(
+            table(TableStyle::Box),
+            "┌──────┬──────┐\n│ name │    x │\n├──────┼──────┤\n│ a|b  │ 2.50 │\n└──────┴──────┘"
+        );
+        assert_eq!(table(TableStyle::Plain), "name     x\na|b   2.50");
+        assert_eq!(
+            table(TableStyle::Psql),
+            " name |    x\n------+------\n a|b  | 2.50\n(1 row)"
+        );
+        assert_eq!(
+            table(TableStyle::Rst),
+            "====  ====\nname     x\n====  ====\na|b   2.50\n====  ===="
+        );
+    }
+
+    #[test]
+    fn test_number_format() {
+        let numbers = NumberFormat {
+            precision: vec!["3".parse().unwrap(), "ID:0".parse().unwrap()],
+            thousands: true,
+        };
+        assert_eq!(numbers.decimals("id"), Some(0));
+        assert_eq!(numbers.decimals("price"), Some(3));
+        assert_eq!(numbers.float(1234567.891, Some(2)), "1,234,567.89");
+        assert_eq!(numbers.float(-1234.5, None), "-1,234.5");
+        assert_eq!(numbers.float(0.00012, Some(2)), "1.20e-4");
+        assert_eq!(numbers.float(-0.001, Some(1)), "-1.0e-3");
+        assert_eq!(numbers.float(2.5e20, None), "2.5e20");
+        assert_eq!(numbers.float(-0.0, Some(1)), "0.0");
+        assert_eq!(numbers.integer(-3456), "-3,456");
+        assert_eq!(NumberFormat::default().integer(3456), "3456");
+
+        assert!("price:".parse::<Precision>().is_err());
+        assert!(":2".parse::<Precision>().is_err());
+        assert!("-1".parse::<Precision>().is_err());
+    }
+
+    #[test]
+    fn test_thousands_only_in_tables() {
+        let records = vec![vec!["3456".to_string(), "1234.5".to_string()]];
+        let dataset =
+            Dataset::from_records(vec!["id".to_string(), "x".to_string()], records).unwrap();
+        let fmt = OutputFormat {
+            numbers: NumberFormat {
+                precision: vec![],
+                thousands: true,
+            },
+            ..Default::default()
+        };
+        let table = render_table(&dataset, &fmt).unwrap();
+        assert!(table.contains(" 3,456 * 1,234.50 "));
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.csv");
+        dump_to_file(&dataset, file_path.clone(), &fmt).unwrap();
+        assert_eq!(
+            fs::read_to_string(file_path).unwrap(),
+            "id,x\n3456,1234.5\n"
+        );
+    }
+
+    #[test]
+    fn test_precision_applies_to_every_output() {
+        let records = vec![vec!["3456".to_string(), "0.123456".to_string()]];
+        let dataset =
+            Dataset::from_records(vec!["id".to_string(), "x".to_string()], records).unwrap();
+        let fmt = OutputFormat {
+            numbers: NumberFormat {
+                precision: vec!["x:3".parse().unwrap()],
+                thousands: false,
+            },
+            ..Default::default()
+        };
+
+        let table = render_table(&dataset, &fmt).unwrap();
+        assert!(table.contains(" 3456 * 0.123 "));
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.csv");
+        dump_to_file(&dataset, file_path.clone(), &fmt).unwrap();
+        assert_eq!(fs::read_to_string(file_path).unwrap(), "id,x\n3456,0.123\n");
+
+        let mut buf = Vec::new();
+        let rows = dataset.rows.iter().map(Ok);
+        write_json(&mut buf, &dataset.headers, rows, &fmt.numbers).unwrap();
+        assert!(String::from_utf8(buf).unwrap().contains("\"x\": 0.123\n"));
+    }
+
+    #[test]
+    fn test_write_json_keeps_header_order() {
+        let records = vec![vec!["b".to_string(), "".to_string()]];
+        let dataset =
+            Dataset::from_records(vec!["z".to_string(), "a".to_string()], records).unwrap();
+
+        let mut buf = Vec::new();
+        let numbers = NumberFormat::default();
+        write_json(
+            &mut buf,
+            &dataset.headers,
+            dataset.rows.iter().map(Ok),
+            &numbers,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "[\n  {\n    \"z\": \"b\",\n    \"a\": null\n  }\n]\n"
+        );
+
+        let mut buf = Vec::new();
+        write_json(
+            &mut buf,
+            &[],
+            std::iter::empty::<Result<Vec<Value>, String>>(),
+            &numbers,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "[]\n");
+    }
+}
@@ -0,0 +1,102 @@
+use resvg::{tiny_skia, usvg};
+
+use crate::{chart::Chart, svg};
+
+/// Resolution the svg layout is drawn at, text and lines scale with the
+/// requested dpi from there.
+pub const BASE_DPI: u32 = 96;
+
+/// Draw `chart` as a png image of `width` by `height` pixels.
+///
+/// The chart is laid out as an svg image of the same physical size and
+/// rasterized with the Noto Sans font compiled into the binary, so no
+/// system fonts are needed. The dpi is recorded in the image.
+pub fn render(chart: &Chart, width: u32, height: u32, dpi: u32) -> Result<Vec<u8>, String> {
+    if dpi == 0 {
+        return Err("The dpi must be positive".to_string());
+    }
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| format!("Image size {width}x{height} is invalid"))?;
+
+    let scale = dpi as f32 / BASE_DPI as f32;
+    let layout = |pixels: u32| ((pixels as f32 / scale).round() as u32).max(1);
+    let document = svg::render(chart, layout(width), layout(height));
+
+    let mut options = usvg::Options::default();
+    let fonts = options.fontdb_mut();
+    fonts.load_font_data(notosans::REGULAR_TTF.to_vec());
+    fonts.set_sans_serif_family("Noto Sans");
+    let tree = usvg::Tree::from_str(&document, &options)
+        .map_err(|err| format!("Failed to draw the chart: {err}"))?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    encode(&pixmap, dpi).map_err(|err| format!("Failed to encode the png image: {err}"))
+}
+
+/// `pixmap` as png bytes, with the dpi as its pixel density.
+fn encode(pixmap: &tiny_skia::Pixmap, dpi: u32) -> Result<Vec<u8>, png::EncodingError> {
+    let mut data = Vec::with_capacity(pixmap.data().len());
+    for pixel in pixmap.pixels() {
+        let color = pixel.demultiply();
+        data.extend([color.red(), color.green(), color.blue(), color.alpha()]);
+    }
+
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, pixmap.width(), pixmap.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    // Pixels per meter, from pixels per inch.
+    let density = (f64::from(dpi) / 0.0254).round() as u32;
+    encoder.set_pixel_dims(Some(png::PixelDimensions {
+        xppu: density,
+        yppu: density,
+        unit: png::Unit::Meter,
+    }));
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dataset;
+
+    fn chart() -> Chart {
+        let records = [["1", "10"], ["2", "20"], ["3", "15"]]
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect();
+        let dataset =
+            Dataset::from_records(["id", "price"].map(String::from).to_vec(), records).unwrap();
+        Chart::line(&dataset, "id", &["price"], &[]).unwrap()
+    }
+
+    #[test]
+    fn test_png_size_and_dpi() {
+        let bytes = render(&chart(), 300, 200, 192).unwrap();
+        let decoder = png::Decoder::new(bytes.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!((info.width, info.height), (300, 200));
+        assert_eq!(info.pixel_dims.unwrap().xppu, 7559);
+
+        // Text and the line leave pixels that are neither white nor clear.
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        let drawn = pixels
+            .chunks(4)
+            .filter(|p| p[..3] != [255, 255, 255])
+            .count();
+        assert!(drawn > 1000);
+        assert!(pixels.chunks(4).all(|p| p[3] == 255));
+
+        assert!(render(&chart(), 0, 200, 96).is_err());
+        assert!(render(&chart(), 300, 200, 0).is_err());
+    }
+}
@@ -0,0 +1,268 @@
+use std::fmt::Write;
+
+use crate::{
+    chart::Chart,
+    dataset::Dataset,
+    output::OutputFormat,
+    stats::BinRule,
+    svg::{self, escape},
+    value::{ColumnType, Value},
+};
+
+/// Size in pixels of the histogram of each numeric column.
+const HISTOGRAM_WIDTH: u32 = 480;
+const HISTOGRAM_HEIGHT: u32 = 300;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+h1 { font-size: 1.6em; }
+h2 { font-size: 1.3em; margin-top: 1.5em; border-bottom: 1px solid #ddd; }
+table { border-collapse: collapse; font-size: 0.9em; }
+th, td { padding: 0.3em 0.8em; border-bottom: 1px solid #eee; }
+th { background: #f4f4f4; text-align: left; }
+td.number { text-align: right; font-variant-numeric: tabular-nums; }
+table.sortable th { cursor: pointer; user-select: none; }
+table.sortable th[data-order=asc]::after { content: ' \\25B2'; }
+table.sortable th[data-order=desc]::after { content: ' \\25BC'; }
+.scroll { overflow-x: auto; }
+.charts { display: flex; flex-wrap: wrap; gap: 1em; }
+figure { margin: 0; }
+";
+
+/// Sorts a preview table by the clicked column, numbers numerically and
+/// empty cells last.
+const SORT_SCRIPT: &str = "
+document.querySelectorAll('table.sortable th').forEach((th, column) => {
+  th.addEventListener('click', () => {
+    const table = th.closest('table');
+    const ascending = th.dataset.order !== 'asc';
+    table.querySelectorAll('th').forEach((other) => delete other.dataset.order);
+    th.dataset.order = ascending ? 'asc' : 'desc';
+    const key = (row) => row.cells[column].dataset.value ?? row.cells[column].textContent;
+    const body = table.tBodies[0];
+    const rows = Array.from(body.rows).sort((a, b) => {
+      const [x, y] = [key(a), key(b)];
+      if (x === '' || y === '') return (x === '') - (y === '');
+      const order = isNaN(x) || isNaN(y) ? x.localeCompare(y) : x - y;
+      return ascending ? order : -order;
+    });
+    body.append(...rows);
+  });
+});
+";
+
+/// What a report shows.
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    /// Rows of the data preview.
+    pub preview_rows: usize,
+    /// Bins of each histogram, picked by `rule` when missing.
+    pub bins: Option<usize>,
+    pub rule: BinRule,
+}
+
+/// A standalone html page summing up `dataset`: its columns, the `describe`
+/// statistics and a histogram of each numeric column, and a preview of its
+/// first rows that sorts when a header is clicked.
+///
+/// Styles, script and charts are all inline, so the page can be shared as a
+/// single file.
+pub fn render(
+    dataset: &Dataset,
+    title: &str,
+    options: &ReportOptions,
+    fmt: &OutputFormat,
+) -> Result<String, String> {
+    let numeric: Vec<&String> = dataset
+        .headers
+        .iter()
+        .zip(&dataset.types)
+        .filter(|(_, ty)| ty.is_numeric())
+        .map(|(header, _)| header)
+        .collect();
+
+    let mut html = String::new();
+    let title = escape(title);
+    let _ = writeln!(html, "<!DOCTYPE html>");
+    let _ = writeln!(html, "<html lang=\"en\">");
+    let _ = writeln!(html, "<head>");
+    let _ = writeln!(html, "<meta charset=\"utf-8\">");
+    let _ = writeln!(html, "<title>{title}</title>");
+    let _ = writeln!(html, "<style>{STYLE}</style>");
+    let _ = writeln!(html, "</head>");
+    let _ = writeln!(html, "<body>");
+    let _ = writeln!(html, "<h1>{title}</h1>");
+    let _ = writeln!(
+        html,
+        "<p>{} rows, {} columns</p>",
+        dataset.rows.len(),
+        dataset.headers.len()
+    );
+
+    let _ = writeln!(html, "<h2>Columns</h2>");
+    html.push_str(&columns_table(dataset));
+
+    let _ = writeln!(html, "<h2>Statistics</h2>");
+    if numeric.is_empty() {
+        let _ = writeln!(html, "<p>No numeric columns.</p>");
+    } else {
+        html.push_str(&html_table(&dataset.describe(&numeric)?, false, fmt));
+
+        let _ = writeln!(html, "<h2>Histograms</h2>");
+        let _ = writeln!(html, "<div class=\"charts\">");
+        for column in &numeric {
+            let _ = writeln!(html, "<figure>");
+            match Chart::histogram(dataset, column, options.bins, options.rule) {
+                Ok(chart) => html.push_str(&svg::render(&chart, HISTOGRAM_WIDTH, HISTOGRAM_HEIGHT)),
+                Err(err) => {
+                    let _ = writeln!(html, "<p>{}: {}</p>", escape(column), escape(&err));
+                }
+            }
+            let _ = writeln!(html, "</figure>");
+        }
+        let _ = writeln!(html, "</div>");
+    }
+
+    let _ = writeln!(html, "<h2>Preview</h2>");
+    let shown = dataset.rows.len().min(options.preview_rows);
+    let _ = writeln!(
+        html,
+        "<p>First {shown} of {} rows, click a header to sort.</p>",
+        dataset.rows.len()
+    );
+    let preview = Dataset {
+        headers: dataset.headers.clone(),
+        types: dataset.types.clone(),
+        rows: dataset.rows[..shown].to_vec(),
+    };
+    html.push_str(&html_table(&preview, true, fmt));
+
+    let _ = writeln!(html, "<script>{SORT_SCRIPT}</script>");
+    let _ = writeln!(html, "</body>");
+    let _ = writeln!(html, "</html>");
+    Ok(html)
+}
+
+/// Name, type and number of empty cells of each column.
+fn columns_table(dataset: &Dataset) -> String {
+    let rows = dataset
+        .headers
+        .iter()
+        .enumerate()
+        .map(|(idx, header)| {
+            let nulls = dataset.rows.iter().filter(|row| row[idx].is_null()).count();
+            vec![
+                Value::Str(header.clone()),
+                Value::Str(dataset.types[idx].to_string()),
+                Value::Int(nulls as i64),
+            ]
+        })
+        .collect();
+    let columns = Dataset {
+        headers: ["column", "type", "nulls"].map(String::from).to_vec(),
+        types: vec![ColumnType::Str, ColumnType::Str, ColumnType::Int],
+        rows,
+    };
+    html_table(&columns, false, &OutputFormat::default())
+}
+
+/// `dataset` as an html table with its cells rendered like the text table,
+/// numbers aligned right. Cells of `sortable` tables keep their raw number
+/// to sort by.
+fn html_table(dataset: &Dataset, sortable: bool, fmt: &OutputFormat) -> String {
+    let mut html = String::new();
+    let class = if sortable { " class=\"sortable\"" } else { "" };
+    let _ = writeln!(html, "<div class=\"scroll\"><table{class}>");
+    let _ = write!(html, "<thead><tr>");
+    for header in &dataset.headers {
+        let _ = write!(html, "<th>{}</th>", escape(header));
+    }
+    let _ = writeln!(html, "</tr></thead>");
+
+    let _ = writeln!(html, "<tbody>");
+    for row in &dataset.rows {
+        let _ = write!(html, "<tr>");
+        for (value, header) in row.iter().zip(&dataset.headers) {
+            let cell = escape(&fmt.render_cell(value, Some(fmt.table_decimals(header))));
+            match value.as_f64() {
+                Some(number) if sortable => {
+                    let _ = write!(
+                        html,
+                        "<td class=\"number\" data-value=\"{number}\">{cell}</td>"
+                    );
+                }
+                Some(_) => {
+                    let _ = write!(html, "<td class=\"number\">{cell}</td>");
+                }
+                None if value.is_null() && sortable => {
+                    let _ = write!(html, "<td data-value=\"\">{cell}</td>");
+                }
+                None => {
+                    let _ = write!(html, "<td>{cell}</td>");
+                }
+            }
+        }
+        let _ = writeln!(html, "</tr>");
+    }
+    let _ = writeln!(html, "</tbody>");
+    let _ = writeln!(html, "</table></div>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dataset() -> Dataset {
+        let records = [
+            ["1", "10.5", "lamp"],
+            ["2", "", "desk & chair"],
+            ["3", "7", "pen"],
+        ]
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+        .collect();
+        let headers = ["id", "price", "name"].map(String::from).to_vec();
+        Dataset::from_records(headers, records).unwrap()
+    }
+
+    fn options(preview_rows: usize) -> ReportOptions {
+        ReportOptions {
+            preview_rows,
+            bins: None,
+            rule: BinRule::Sturges,
+        }
+    }
+
+    #[test]
+    fn test_report_sections() {
+        let fmt = OutputFormat::default();
+        let html = render(&dataset(), "sales <2024>.csv", &options(2), &fmt).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>sales &lt;2024&gt;.csv</title>"));
+        assert!(html.contains("<p>3 rows, 3 columns</p>"));
+        assert!(html.contains("<td>price</td><td>float</td><td class=\"number\">1</td>"));
+        assert!(html.contains("<td>name</td><td>string</td>"));
+        // One histogram per numeric column, inline and without any link to
+        // outside resources.
+        assert_eq!(html.matches("<svg xmlns").count(), 2);
+        assert!(!html.contains("src=") && !html.contains("href="));
+        assert!(html.contains("<th>statistic</th><th>id</th><th>price</th>"));
+
+        let preview = html.split("<h2>Preview</h2>").nth(1).unwrap();
+        assert!(preview.contains("First 2 of 3 rows"));
+        assert!(preview.contains("<table class=\"sortable\">"));
+        assert!(preview.contains("<td class=\"number\" data-value=\"10.5\">10.50</td>"));
+        assert!(preview.contains("<td data-value=\"\"></td><td>desk &amp; chair</td>"));
+        assert!(!preview.contains("<td>pen</td>"));
+    }
+
+    #[test]
+    fn test_report_without_numbers() {
+        let dataset = dataset().select(&["name".parse().unwrap()], &[]).unwrap();
+        let html = render(&dataset, "names", &options(10), &OutputFormat::default()).unwrap();
+        assert!(html.contains("<p>No numeric columns.</p>"));
+        assert!(!html.contains("<svg"));
+        assert!(html.contains("First 3 of 3 rows"));
+    }
+}
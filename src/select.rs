@@ -0,0 +1,233 @@
+use std::str::FromStr;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::dataset::Columns;
+
+/// Columns named by a `select` argument: a plain name, a glob such as
+/// `value*` or `id_?`, or a regular expression between slashes such as
+/// `/^value[0-9]+$/`. Matching ignores case, like every column lookup.
+#[derive(Debug, Clone)]
+pub enum ColumnPattern {
+    Name(String),
+    /// A glob or regular expression, with the text it was written as.
+    Pattern(String, Regex),
+}
+
+impl ColumnPattern {
+    pub fn matches(&self, header: &str) -> bool {
+        match self {
+            Self::Name(name) => name.to_lowercase() == header,
+            Self::Pattern(_, regex) => regex.is_match(header),
+        }
+    }
+
+    /// Indices of the matching columns, in header order.
+    fn indices(&self, columns: &impl Columns) -> Vec<usize> {
+        match self {
+            Self::Name(name) => columns.column_index(name).into_iter().collect(),
+            Self::Pattern(..) => (0..columns.headers().len())
+                .filter(|&idx| self.matches(&columns.headers()[idx]))
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for ColumnPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let source = if let Some(regex) = s.strip_prefix('/').and_then(|s| s.strip_suffix('/')) {
+            regex.to_string()
+        } else if s.contains(['*', '?']) {
+            let glob = s
+                .split('*')
+                .map(|part| {
+                    part.split('?')
+                        .map(regex::escape)
+                        .collect::<Vec<_>>()
+                        .join(".")
+                })
+                .collect::<Vec<_>>()
+                .join(".*");
+            format!("^{glob}$")
+        } else if s.is_empty() {
+            return Err("Empty column name".to_string());
+        } else {
+            return Ok(Self::Name(s.to_string()));
+        };
+
+        RegexBuilder::new(&source)
+            .case_insensitive(true)
+            .build()
+            .map(|regex| Self::Pattern(s.to_string(), regex))
+            .map_err(|err| format!("Invalid column pattern '{s}': {err}"))
+    }
+}
+
+/// One argument of `select`: a [`ColumnPattern`], or `COLUMN:NEW_NAME` to
+/// rename a single column.
+#[derive(Debug, Clone)]
+pub struct Selection {
+    pub pattern: ColumnPattern,
+    pub rename: Option<String>,
+}
+
+impl FromStr for Selection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let is_regex = s.starts_with('/') && s.ends_with('/');
+        let (pattern, rename) = match s.rsplit_once(':') {
+            Some((column, rename)) if !is_regex => (column, Some(rename.trim().to_lowercase())),
+            _ => (s, None),
+        };
+
+        let pattern: ColumnPattern = pattern.parse()?;
+        match (&pattern, &rename) {
+            (_, Some(rename)) if rename.is_empty() => Err(format!("Missing new name in '{s}'")),
+            (ColumnPattern::Pattern(..), Some(_)) => {
+                Err(format!("Only a single column can be renamed, not '{s}'"))
+            }
+            _ => Ok(Self { pattern, rename }),
+        }
+    }
+}
+
+/// Indices and output headers of the selected columns.
+///
+/// Without `selections` every column is kept in order. Each selection adds
+/// its columns, and must match at least one. Patterns skip the columns that
+/// are selected already; a name skips its column only when it is selected
+/// under the same name, so a column can be selected again with a new name,
+/// though not renamed to the name of another selected column. Columns
+/// matching an `exclude` pattern are dropped afterwards.
+pub fn resolve(
+    columns: &impl Columns,
+    selections: &[Selection],
+    exclude: &[ColumnPattern],
+) -> Result<Vec<(usize, String)>, String> {
+    let headers = columns.headers();
+    let mut selected: Vec<(usize, String)> = Vec::new();
+
+    if selections.is_empty() {
+        selected.extend(headers.iter().cloned().enumerate());
+    }
+    for selection in selections {
+        let indices = selection.pattern.indices(columns);
+        if indices.is_empty() {
+            return Err(match &selection.pattern {
+                ColumnPattern::Name(name) => format!("Invalid category: {name}"),
+                ColumnPattern::Pattern(text, _) => format!("No column matches '{text}'"),
+            });
+        }
+        for idx in indices {
+            let name = selection
+                .rename
+                .clone()
+                .unwrap_or_else(|| headers[idx].clone());
+            let skip = match selection.pattern {
+                ColumnPattern::Name(_) => selected.contains(&(idx, name.clone())),
+                ColumnPattern::Pattern(..) => selected.iter().any(|(other, _)| *other == idx),
+            };
+            if !skip {
+                selected.push((idx, name));
+            }
+        }
+    }
+
+    selected.retain(|(idx, _)| {
+        !exclude
+            .iter()
+            .any(|pattern| pattern.matches(&headers[*idx]))
+    });
+    if selected.is_empty() {
+        return Err("No columns left to select".to_string());
+    }
+    for (i, (idx, name)) in selected.iter().enumerate() {
+        let renamed = *name != headers[*idx];
+        let clash = selected
+            .iter()
+            .enumerate()
+            .any(|(j, (_, other))| j != i && other == name);
+        if renamed && clash {
+            return Err(format!(
+                "Cannot rename '{}' to '{name}', another selected column has that name",
+                headers[*idx]
+            ));
+        }
+    }
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::Dataset;
+
+    fn dataset() -> Dataset {
+        let headers = ["id", "value1", "value2", "value10", "name"];
+        Dataset::from_records(headers.map(String::from).to_vec(), vec![]).unwrap()
+    }
+
+    fn names(selections: &[&str], exclude: &[&str]) -> Result<Vec<String>, String> {
+        let selections: Vec<Selection> = selections.iter().map(|s| s.parse().unwrap()).collect();
+        let exclude: Vec<ColumnPattern> = exclude.iter().map(|s| s.parse().unwrap()).collect();
+        let selected = resolve(&dataset(), &selections, &exclude)?;
+        Ok(selected.into_iter().map(|(_, name)| name).collect())
+    }
+
+    #[test]
+    fn test_select_rename_and_reorder() {
+        assert_eq!(
+            names(&["name", "ID:key", "value2"], &[]).unwrap(),
+            ["name", "key", "value2"]
+        );
+        assert_eq!(
+            names(&["value*", "id"], &["value1?"]).unwrap(),
+            ["value1", "value2", "id"]
+        );
+        assert_eq!(
+            names(&["/^value[0-9]$/"], &[]).unwrap(),
+            ["value1", "value2"]
+        );
+        assert_eq!(names(&[], &["value*"]).unwrap(), ["id", "name"]);
+        // A column can be selected again under another name.
+        assert_eq!(
+            names(&["value1", "value1:v1", "value*", "id:key", "id"], &[]).unwrap(),
+            ["value1", "v1", "value2", "value10", "key", "id"]
+        );
+        assert_eq!(
+            names(&["id", "value1:score", "value*"], &[]).unwrap(),
+            ["id", "score", "value2", "value10"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_selections() {
+        assert_eq!(
+            names(&["price"], &[]).unwrap_err(),
+            "Invalid category: price"
+        );
+        assert_eq!(
+            names(&["cost*"], &[]).unwrap_err(),
+            "No column matches 'cost*'"
+        );
+        assert_eq!(
+            names(&["id"], &["id"]).unwrap_err(),
+            "No columns left to select"
+        );
+        assert_eq!(
+            names(&["name", "id:name"], &[]).unwrap_err(),
+            "Cannot rename 'id' to 'name', another selected column has that name"
+        );
+        assert_eq!(
+            names(&["id:key", "value1:key"], &[]).unwrap_err(),
+            "Cannot rename 'id' to 'key', another selected column has that name"
+        );
+        assert!(names(&["id:name"], &[]).is_ok());
+        assert!("value*:v".parse::<Selection>().is_err());
+        assert!("id:".parse::<Selection>().is_err());
+        assert!("/(/".parse::<ColumnPattern>().is_err());
+    }
+}
@@ -0,0 +1,318 @@
+use std::{collections::BTreeMap, f64::consts::PI, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+/// Default number of centroids a [`QuantileSketch`] aims for.
+pub const DEFAULT_COMPRESSION: f64 = 100.0;
+
+/// Values buffered per unit of compression before they are merged into centroids.
+const BUFFER_FACTOR: usize = 5;
+
+/// Mean and weight of a cluster of nearby values.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Centroid {
+    pub mean: f64,
+    pub weight: f64,
+}
+
+/// Mergeable t-digest estimating quantiles of a column in one pass and
+/// bounded memory.
+///
+/// Values are clustered into centroids that are small near the tails, so
+/// extreme quantiles like p99.9 stay accurate. Sketches of parts of a column
+/// can be merged, and serialize to json without their pending buffer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SavedSketch", into = "SavedSketch")]
+pub struct QuantileSketch {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    /// Values and centroids waiting to be merged into `centroids`.
+    buffer: Vec<Centroid>,
+    count: f64,
+    nulls: u64,
+    min: f64,
+    max: f64,
+}
+
+/// Serialized form of a [`QuantileSketch`], with the buffer merged.
+#[derive(Serialize, Deserialize)]
+struct SavedSketch {
+    compression: f64,
+    count: f64,
+    nulls: u64,
+    min: f64,
+    max: f64,
+    centroids: Vec<Centroid>,
+}
+
+impl From<QuantileSketch> for SavedSketch {
+    fn from(mut sketch: QuantileSketch) -> Self {
+        sketch.compress();
+        Self {
+            compression: sketch.compression,
+            count: sketch.count,
+            nulls: sketch.nulls,
+            min: sketch.min,
+            max: sketch.max,
+            centroids: sketch.centroids,
+        }
+    }
+}
+
+impl From<SavedSketch> for QuantileSketch {
+    fn from(saved: SavedSketch) -> Self {
+        Self {
+            compression: saved.compression,
+            centroids: saved.centroids,
+            buffer: Vec::new(),
+            count: saved.count,
+            nulls: saved.nulls,
+            min: saved.min,
+            max: saved.max,
+        }
+    }
+}
+
+impl Default for QuantileSketch {
+    fn default() -> Self {
+        Self::new(DEFAULT_COMPRESSION)
+    }
+}
+
+impl QuantileSketch {
+    /// A larger `compression` keeps more centroids and gives tighter estimates.
+    pub fn new(compression: f64) -> Self {
+        Self {
+            compression,
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+            count: 0.0,
+            nulls: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Number of values seen, nulls excluded.
+    pub fn count(&self) -> u64 {
+        self.count as u64
+    }
+
+    /// Number of null cells seen.
+    pub fn nulls(&self) -> u64 {
+        self.nulls
+    }
+
+    pub fn push(&mut self, value: f64) {
+        self.add(Centroid {
+            mean: value,
+            weight: 1.0,
+        });
+    }
+
+    pub fn push_null(&mut self) {
+        self.nulls += 1;
+    }
+
+    /// Fold the values seen by `other` into this sketch.
+    pub fn merge(&mut self, other: &QuantileSketch) {
+        for &centroid in other.centroids.iter().chain(&other.buffer) {
+            self.add(centroid);
+        }
+        self.nulls += other.nulls;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    fn add(&mut self, centroid: Centroid) {
+        self.count += centroid.weight;
+        self.min = self.min.min(centroid.mean);
+        self.max = self.max.max(centroid.mean);
+        self.buffer.push(centroid);
+        if self.buffer.len() >= BUFFER_FACTOR * self.compression as usize {
+            self.compress();
+        }
+    }
+
+    /// Merge the buffer into the centroids, keeping each centroid within the
+    /// size the `k1` scale function allows at its quantile.
+    fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut all = std::mem::take(&mut self.centroids);
+        all.append(&mut self.buffer);
+        all.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+
+        let total = self.count;
+        let mut merged = Vec::new();
+        let mut current = all[0];
+        let mut before = 0.0;
+        let mut limit = total * self.quantile_limit(0.0);
+
+        for &next in &all[1..] {
+            if before + current.weight + next.weight <= limit {
+                let weight = current.weight + next.weight;
+                current.mean += (next.mean - current.mean) * next.weight / weight;
+                current.weight = weight;
+            } else {
+                before += current.weight;
+                merged.push(current);
+                limit = total * self.quantile_limit(before / total);
+                current = next;
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+
+    /// Largest quantile a centroid starting at quantile `q` may reach.
+    fn quantile_limit(&self, q: f64) -> f64 {
+        let scale = self.compression / (2.0 * PI);
+        let k = scale * (2.0 * q - 1.0).asin() + 1.0;
+        if k >= scale * PI / 2.0 {
+            return 1.0;
+        }
+        ((k / scale).sin() + 1.0) / 2.0
+    }
+
+    /// Estimate of the quantile `q` (between 0 and 1), interpolating linearly
+    /// like [`crate::stats::QuantileMethod::Linear`]. Exact while every
+    /// value still has a centroid of its own.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let mut sketch = self.clone();
+        sketch.compress();
+        let centroids = &sketch.centroids;
+        let (first, last) = (centroids.first()?, centroids.last()?);
+        let total = sketch.count;
+        if total <= 1.0 {
+            return Some(first.mean);
+        }
+
+        // Weight below the center of the value at linear rank q * (n - 1),
+        // from 0.5 for the smallest value to n - 0.5 for the largest.
+        let index = q.clamp(0.0, 1.0) * (total - 1.0) + 0.5;
+        if index < first.weight / 2.0 {
+            let span = first.weight / 2.0 - 0.5;
+            return Some(interpolate(sketch.min, first.mean, index - 0.5, span));
+        }
+
+        let mut center = first.weight / 2.0;
+        for pair in centroids.windows(2) {
+            let next_center = center + (pair[0].weight + pair[1].weight) / 2.0;
+            if index <= next_center {
+                let span = next_center - center;
+                return Some(interpolate(
+                    pair[0].mean,
+                    pair[1].mean,
+                    index - center,
+                    span,
+                ));
+            }
+            center = next_center;
+        }
+
+        let span = total - 0.5 - center;
+        Some(interpolate(last.mean, sketch.max, index - center, span))
+    }
+}
+
+/// The value `offset` of the way along `span` from `from` to `to`, or `from`
+/// when the span is empty.
+fn interpolate(from: f64, to: f64, offset: f64, span: f64) -> f64 {
+    if span > 0.0 {
+        from + (to - from) * offset / span
+    } else {
+        from
+    }
+}
+
+/// Sketches saved per column name.
+pub type ColumnSketches = BTreeMap<String, QuantileSketch>;
+
+pub fn read_sketches(path: &Path) -> Result<ColumnSketches, String> {
+    let json = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read sketch {}: {err}", path.display()))?;
+    serde_json::from_str(&json).map_err(|err| format!("Invalid sketch {}: {err}", path.display()))
+}
+
+pub fn write_sketches(path: &Path, sketches: &ColumnSketches) -> Result<(), String> {
+    let json = serde_json::to_string(sketches).map_err(|err| format!("Serialize sketch: {err}"))?;
+    fs::write(path, json).map_err(|err| format!("Failed to write sketch {}: {err}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic shuffle of 0..n.
+    fn shuffled(n: u64) -> impl Iterator<Item = f64> {
+        (0..n).map(move |i| ((i * 7919) % n) as f64)
+    }
+
+    #[test]
+    fn test_single_value() {
+        let mut sketch = QuantileSketch::default();
+        sketch.push(5.0);
+        for q in [0.0, 0.5, 0.9, 1.0] {
+            assert_eq!(sketch.quantile(q), Some(5.0));
+        }
+    }
+
+    #[test]
+    fn test_small_inputs_are_exact() {
+        let mut sketch = QuantileSketch::default();
+        for value in [4.0, 1.0, 5.0, 3.0, 2.0] {
+            sketch.push(value);
+        }
+        assert_eq!(sketch.quantile(0.0), Some(1.0));
+        assert_eq!(sketch.quantile(0.125), Some(1.5));
+        assert_eq!(sketch.quantile(0.5), Some(3.0));
+        assert_eq!(sketch.quantile(1.0), Some(5.0));
+        assert_eq!(QuantileSketch::default().quantile(0.5), None);
+    }
+
+    #[test]
+    fn test_bounded_error_on_large_inputs() {
+        let n = 100_000;
+        let mut sketch = QuantileSketch::default();
+        shuffled(n).for_each(|value| sketch.push(value));
+        assert!(sketch.centroids.len() + sketch.buffer.len() < 1000);
+
+        for q in [0.01, 0.25, 0.5, 0.9, 0.99, 0.999] {
+            let exact = q * (n - 1) as f64;
+            let estimate = sketch.quantile(q).unwrap();
+            assert!(
+                (estimate - exact).abs() / (n as f64) < 0.005,
+                "q={q}: {estimate} vs {exact}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_and_serialize() {
+        let n = 20_000;
+        let (mut low, mut high) = (QuantileSketch::default(), QuantileSketch::default());
+        for value in shuffled(n) {
+            if value < 5000.0 {
+                low.push(value)
+            } else {
+                high.push(value)
+            }
+        }
+        high.push_null();
+
+        let json = serde_json::to_string(&high).unwrap();
+        let restored: QuantileSketch = serde_json::from_str(&json).unwrap();
+        assert!(restored.buffer.is_empty());
+        let (restored_median, median) = (restored.quantile(0.5), high.quantile(0.5));
+        assert!((restored_median.unwrap() - median.unwrap()).abs() < 1e-6);
+
+        low.merge(&restored);
+        assert_eq!(low.count(), n);
+        assert_eq!(low.nulls(), 1);
+        let median = low.quantile(0.5).unwrap();
+        assert!((median - 9999.5).abs() < 100.0, "{median}");
+        assert_eq!(low.quantile(1.0), Some((n - 1) as f64));
+    }
+}
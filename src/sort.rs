@@ -0,0 +1,232 @@
+use std::{cmp::Ordering, str::FromStr};
+
+use clap::ValueEnum;
+
+use crate::{dataset::Columns, value::Value};
+
+/// Where null cells go, whatever the direction of the key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum NullOrder {
+    /// Nulls before every value
+    First,
+    /// Nulls after every value
+    #[default]
+    Last,
+}
+
+/// One key of a sort, written `COLUMN[:asc|desc]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub column: String,
+    pub descending: bool,
+}
+
+impl SortKey {
+    pub fn new(column: &str, descending: bool) -> Self {
+        Self {
+            column: column.to_string(),
+            descending,
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (column, descending) = match s.rsplit_once(':') {
+            Some((column, direction)) => match direction.to_lowercase().as_str() {
+                "asc" => (column, false),
+                "desc" => (column, true),
+                _ => {
+                    return Err(format!(
+                        "Invalid sort direction '{direction}', expected asc or desc"
+                    ));
+                }
+            },
+            None => (s, false),
+        };
+        if column.is_empty() {
+            return Err(format!("Missing column in sort key '{s}'"));
+        }
+        Ok(Self::new(column, descending))
+    }
+}
+
+/// Settings shared by every key of a sort.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SortOptions {
+    pub nulls: NullOrder,
+    /// Compare digit runs in text by their value, so "item2" sorts before "item10".
+    pub natural: bool,
+}
+
+/// Row ordering over several keys, resolved against a set of columns.
+/// Later keys break ties of earlier ones.
+#[derive(Debug, Clone)]
+pub struct RowOrder {
+    keys: Vec<(usize, bool)>,
+    options: SortOptions,
+}
+
+impl RowOrder {
+    pub fn new(
+        columns: &impl Columns,
+        keys: &[SortKey],
+        options: SortOptions,
+    ) -> Result<Self, String> {
+        if keys.is_empty() {
+            return Err("No sort keys passed".to_string());
+        }
+        let keys = keys
+            .iter()
+            .map(|key| {
+                let idx = columns
+                    .column_index(&key.column)
+                    .ok_or_else(|| format!("Invalid category: {}", key.column))?;
+                Ok((idx, key.descending))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self { keys, options })
+    }
+
+    pub fn compare(&self, a: &[Value], b: &[Value]) -> Ordering {
+        self.keys
+            .iter()
+            .map(|&(idx, descending)| self.compare_values(&a[idx], &b[idx], descending))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+
+    fn compare_values(&self, a: &Value, b: &Value, descending: bool) -> Ordering {
+        let nulls_first = self.options.nulls == NullOrder::First;
+        let ordering = match (a, b) {
+            (Value::Null, Value::Null) => return Ordering::Equal,
+            (Value::Null, _) if nulls_first => return Ordering::Less,
+            (Value::Null, _) => return Ordering::Greater,
+            (_, Value::Null) if nulls_first => return Ordering::Greater,
+            (_, Value::Null) => return Ordering::Less,
+            (Value::Str(a), Value::Str(b)) if self.options.natural => natural_cmp(a, b),
+            _ => a.total_cmp(b),
+        };
+
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+/// Compare text with runs of digits ordered by their numeric value.
+/// Text that only differs in leading zeros falls back to a lexical comparison.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    natural_runs_cmp(a, b).then_with(|| a.cmp(b))
+}
+
+fn natural_runs_cmp(mut a: &str, mut b: &str) -> Ordering {
+    loop {
+        let (Some(ca), Some(cb)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+
+        let ordering = if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let (digits_a, rest_a) = split_digits(a);
+            let (digits_b, rest_b) = split_digits(b);
+            a = rest_a;
+            b = rest_b;
+
+            let (trimmed_a, trimmed_b) = (
+                digits_a.trim_start_matches('0'),
+                digits_b.trim_start_matches('0'),
+            );
+            trimmed_a
+                .len()
+                .cmp(&trimmed_b.len())
+                .then_with(|| trimmed_a.cmp(trimmed_b))
+        } else {
+            a = &a[ca.len_utf8()..];
+            b = &b[cb.len_utf8()..];
+            ca.cmp(&cb)
+        };
+
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+}
+
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::Dataset;
+
+    #[test]
+    fn test_natural_order() {
+        let mut items = ["item10", "item2", "Item1", "item02", "item", "item2b"];
+        items.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            items,
+            ["Item1", "item", "item02", "item2", "item2b", "item10"]
+        );
+    }
+
+    #[test]
+    fn test_parse_sort_keys() {
+        assert_eq!("region:asc".parse(), Ok(SortKey::new("region", false)));
+        assert_eq!("price:DESC".parse(), Ok(SortKey::new("price", true)));
+        assert_eq!("id".parse(), Ok(SortKey::new("id", false)));
+        assert!("price:down".parse::<SortKey>().is_err());
+    }
+
+    #[test]
+    fn test_multiple_keys_and_nulls() {
+        let records = [
+            ["west", "3", "1"],
+            ["east", "", "2"],
+            ["west", "5", "3"],
+            ["east", "4", "4"],
+            ["west", "3", "5"],
+        ]
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+        .collect();
+        let headers = ["region", "price", "id"].map(String::from).to_vec();
+        let dataset = Dataset::from_records(headers, records).unwrap();
+
+        let ids = |keys: &[&str], nulls| {
+            let keys: Vec<SortKey> = keys.iter().map(|key| key.parse().unwrap()).collect();
+            let options = SortOptions {
+                nulls,
+                natural: false,
+            };
+            let mut dataset = dataset.clone();
+            dataset.sort(&keys, options).unwrap();
+            dataset
+                .rows
+                .iter()
+                .map(|row| row[2].to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            ids(&["region:asc", "price:desc"], NullOrder::Last),
+            ["4", "2", "3", "1", "5"]
+        );
+        assert_eq!(
+            ids(&["region:asc", "price:desc"], NullOrder::First),
+            ["2", "4", "3", "1", "5"]
+        );
+        assert_eq!(
+            ids(&["price:desc", "id:desc"], NullOrder::Last),
+            ["3", "4", "5", "1", "2"]
+        );
+        // Equal keys keep their input order.
+        assert_eq!(ids(&["price"], NullOrder::First), ["2", "1", "5", "4", "3"]);
+    }
+}
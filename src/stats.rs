@@ -0,0 +1,767 @@
+use std::{cmp::Ordering, collections::BTreeMap, iter::zip};
+
+use clap::ValueEnum;
+
+use crate::{
+    extsort::{ExternalSorter, SORT_CHUNK_ROWS},
+    sketch::QuantileSketch,
+    value::{ColumnType, Value},
+};
+
+/// Single-pass aggregates computed by [`Accumulator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Aggregate {
+    Mean,
+    Sum,
+    Min,
+    Max,
+    Count,
+}
+
+impl Aggregate {
+    /// Type of the values this aggregate produces over a column of type `input`.
+    pub fn result_type(&self, input: ColumnType) -> ColumnType {
+        match self {
+            Self::Count => ColumnType::Int,
+            Self::Sum | Self::Min | Self::Max if input == ColumnType::Int => ColumnType::Int,
+            _ => ColumnType::Float,
+        }
+    }
+}
+
+/// Running statistics of one column, updated a row at a time.
+#[derive(Debug, Default, Clone)]
+pub struct Accumulator {
+    count: usize,
+    nulls: usize,
+    /// Cells that are neither numbers nor null, only seen by `Count`.
+    others: usize,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    /// A float was seen, so sums and extremes are floats too.
+    floats: bool,
+    /// Exact sum of the integers, unless it overflowed.
+    int_sum: Option<i64>,
+    /// Exact extremes of the integers, which f64 rounds past 2^53.
+    int_min: Option<i64>,
+    int_max: Option<i64>,
+}
+
+impl Accumulator {
+    /// Null cells seen so far.
+    pub fn nulls(&self) -> usize {
+        self.nulls
+    }
+
+    pub fn push(&mut self, value: &Value) {
+        match value.as_f64() {
+            Some(number) => {
+                match value {
+                    Value::Int(int) => {
+                        self.int_sum = if self.count == 0 {
+                            Some(*int)
+                        } else {
+                            self.int_sum.and_then(|sum| sum.checked_add(*int))
+                        };
+                        self.int_min = Some(self.int_min.map_or(*int, |min| min.min(*int)));
+                        self.int_max = Some(self.int_max.map_or(*int, |max| max.max(*int)));
+                    }
+                    _ => self.floats = true,
+                }
+                self.count += 1;
+                self.sum += number;
+                self.min = Some(self.min.map_or(number, |min| min.min(number)));
+                self.max = Some(self.max.map_or(number, |max| max.max(number)));
+            }
+            None if value.is_null() => self.nulls += 1,
+            None => self.others += 1,
+        }
+    }
+
+    /// Nulls are skipped unless `include_nulls` is set, in which case any
+    /// null makes the result null. `Count` counts them instead.
+    pub fn finish(&self, aggregate: Aggregate, include_nulls: bool) -> Value {
+        if let Aggregate::Count = aggregate {
+            let count = if include_nulls {
+                self.count + self.others + self.nulls
+            } else {
+                self.count + self.others
+            };
+            return Value::Int(count as i64);
+        }
+        if include_nulls && self.nulls > 0 {
+            return Value::Null;
+        }
+
+        // Extremes and sums of integer columns stay integers.
+        let ints = !self.floats;
+        let result = match aggregate {
+            Aggregate::Mean => (self.count > 0).then(|| self.sum / self.count as f64),
+            Aggregate::Sum => match self.int_sum {
+                Some(sum) if ints => return Value::Int(sum),
+                _ => (self.count > 0).then_some(self.sum),
+            },
+            Aggregate::Min | Aggregate::Max => {
+                let (extreme, int_extreme) = match aggregate {
+                    Aggregate::Min => (self.min, self.int_min),
+                    _ => (self.max, self.int_max),
+                };
+                match int_extreme {
+                    Some(extreme) if ints => return Value::Int(extreme),
+                    _ => extreme,
+                }
+            }
+            Aggregate::Count => unreachable!(),
+        };
+        result.map_or(Value::Null, Value::Float)
+    }
+}
+
+/// Aggregate the columns at `indices` in one pass over `rows`.
+pub fn aggregate<I, R>(
+    rows: I,
+    indices: &[usize],
+    aggregate: Aggregate,
+    include_nulls: bool,
+) -> Result<Vec<Value>, String>
+where
+    I: Iterator<Item = Result<R, String>>,
+    R: AsRef<[Value]>,
+{
+    let mut accumulators = vec![Accumulator::default(); indices.len()];
+    for row in rows {
+        let row = row?;
+        for (acc, &idx) in zip(&mut accumulators, indices) {
+            acc.push(&row.as_ref()[idx]);
+        }
+    }
+
+    Ok(accumulators
+        .iter()
+        .map(|acc| acc.finish(aggregate, include_nulls))
+        .collect())
+}
+
+fn cmp_first(a: &[Value], b: &[Value]) -> Ordering {
+    a[0].total_cmp(&b[0])
+}
+
+/// How a quantile that falls between two values is computed, as in numpy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum QuantileMethod {
+    /// Interpolate linearly between the two values
+    #[default]
+    Linear,
+    /// The smaller value
+    Lower,
+    /// The larger value
+    Higher,
+    /// The value of the nearest rank, the even rank on ties
+    Nearest,
+    /// The average of the two values
+    Midpoint,
+}
+
+impl QuantileMethod {
+    /// Value at fractional rank `rank`, given the values at its floor and ceiling.
+    fn interpolate(self, rank: f64, lower: f64, upper: f64) -> f64 {
+        let fract = rank.fract();
+        match self {
+            Self::Linear => lower + (upper - lower) * fract,
+            Self::Lower => lower,
+            Self::Higher => upper,
+            Self::Nearest if fract < 0.5 => lower,
+            Self::Nearest if fract == 0.5 && (rank.floor() as usize).is_multiple_of(2) => lower,
+            Self::Nearest => upper,
+            Self::Midpoint if fract == 0.0 => lower,
+            Self::Midpoint => (lower + upper) / 2.0,
+        }
+    }
+}
+
+/// Values at the quantiles `qs` (between 0 and 1) of the values fed to
+/// `sorter`, taken from the two nearest values with `method`. All are
+/// null when the sorter is empty.
+fn sorted_quantiles<F>(
+    sorter: ExternalSorter<F>,
+    qs: &[f64],
+    method: QuantileMethod,
+) -> Result<Vec<Value>, String>
+where
+    F: Fn(&[Value], &[Value]) -> Ordering,
+{
+    if sorter.is_empty() {
+        return Ok(vec![Value::Null; qs.len()]);
+    }
+    let last = (sorter.len() - 1) as f64;
+
+    // Ranks of the sorted values each quantile lies between.
+    let mut needed: BTreeMap<usize, f64> = BTreeMap::new();
+    for q in qs {
+        let rank = q * last;
+        needed.insert(rank.floor() as usize, f64::NAN);
+        needed.insert(rank.ceil() as usize, f64::NAN);
+    }
+
+    let mut sorted = sorter.finish()?.enumerate();
+    for (rank, value) in needed.iter_mut() {
+        for (i, row) in sorted.by_ref() {
+            let row = row?;
+            if i == *rank {
+                *value = row[0].as_f64().unwrap_or(f64::NAN);
+                break;
+            }
+        }
+    }
+
+    Ok(qs
+        .iter()
+        .map(|q| {
+            let rank = q * last;
+            let lower = needed[&(rank.floor() as usize)];
+            let upper = needed[&(rank.ceil() as usize)];
+            Value::Float(method.interpolate(rank, lower, upper))
+        })
+        .collect())
+}
+
+/// Quantiles `qs` (between 0 and 1) of the columns at `indices`.
+///
+/// The result holds one row per quantile with one value per column. Each
+/// column's values go through an external sort, so columns larger than
+/// memory spill to temporary files.
+pub fn quantiles<I, R>(
+    rows: I,
+    indices: &[usize],
+    qs: &[f64],
+    method: QuantileMethod,
+    include_nulls: bool,
+) -> Result<Vec<Vec<Value>>, String>
+where
+    I: Iterator<Item = Result<R, String>>,
+    R: AsRef<[Value]>,
+{
+    check_quantiles(qs)?;
+    let mut sorters: Vec<_> = indices
+        .iter()
+        .map(|_| ExternalSorter::new(SORT_CHUNK_ROWS, cmp_first))
+        .collect();
+    let mut has_nulls = vec![false; indices.len()];
+
+    for row in rows {
+        let row = row?;
+        for (i, &idx) in indices.iter().enumerate() {
+            let value = &row.as_ref()[idx];
+            match value.as_f64() {
+                Some(num) => sorters[i].push(vec![Value::Float(num)])?,
+                None => has_nulls[i] |= value.is_null(),
+            }
+        }
+    }
+
+    let columns = zip(sorters, has_nulls)
+        .map(|(sorter, has_nulls)| {
+            if include_nulls && has_nulls {
+                Ok(vec![Value::Null; qs.len()])
+            } else {
+                sorted_quantiles(sorter, qs, method)
+            }
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok((0..qs.len())
+        .map(|q| columns.iter().map(|column| column[q].clone()).collect())
+        .collect())
+}
+
+fn check_quantiles(qs: &[f64]) -> Result<(), String> {
+    match qs.iter().find(|q| !(0.0..=1.0).contains(*q)) {
+        Some(q) => Err(format!("Quantile {q} is not between 0 and 1")),
+        None => Ok(()),
+    }
+}
+
+/// One [`QuantileSketch`] per column at `indices`, built in a single pass
+/// in bounded memory.
+pub fn sketch<I, R>(rows: I, indices: &[usize]) -> Result<Vec<QuantileSketch>, String>
+where
+    I: Iterator<Item = Result<R, String>>,
+    R: AsRef<[Value]>,
+{
+    let mut sketches = vec![QuantileSketch::default(); indices.len()];
+    for row in rows {
+        let row = row?;
+        for (sketch, &idx) in zip(&mut sketches, indices) {
+            let value = &row.as_ref()[idx];
+            match value.as_f64() {
+                Some(num) => sketch.push(num),
+                None if value.is_null() => sketch.push_null(),
+                None => {}
+            }
+        }
+    }
+    Ok(sketches)
+}
+
+/// Estimates of the quantiles `qs` from a sketch per column, laid out like
+/// [`quantiles`].
+pub fn sketch_quantiles(
+    sketches: &[QuantileSketch],
+    qs: &[f64],
+    include_nulls: bool,
+) -> Result<Vec<Vec<Value>>, String> {
+    check_quantiles(qs)?;
+    Ok(qs
+        .iter()
+        .map(|&q| {
+            sketches
+                .iter()
+                .map(|sketch| match sketch.quantile(q) {
+                    Some(value) if !include_nulls || sketch.nulls() == 0 => Value::Float(value),
+                    _ => Value::Null,
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// Median of the columns at `indices`, the 0.5 quantile of [`quantiles`].
+pub fn median<I, R>(
+    rows: I,
+    indices: &[usize],
+    method: QuantileMethod,
+    include_nulls: bool,
+) -> Result<Vec<Value>, String>
+where
+    I: Iterator<Item = Result<R, String>>,
+    R: AsRef<[Value]>,
+{
+    Ok(quantiles(rows, indices, &[0.5], method, include_nulls)?.remove(0))
+}
+
+/// Statistics computed by [`describe`], in order.
+pub const DESCRIBE_STATISTICS: [&str; 13] = [
+    "count",
+    "null_count",
+    "min",
+    "max",
+    "sum",
+    "mean",
+    "std",
+    "var",
+    "p25",
+    "p50",
+    "p75",
+    "skewness",
+    "kurtosis",
+];
+
+/// Running central moments of one column, updated a value at a time.
+#[derive(Debug, Default, Clone)]
+struct Moments {
+    n: f64,
+    mean: f64,
+    m2: f64,
+    m3: f64,
+    m4: f64,
+}
+
+impl Moments {
+    fn push(&mut self, x: f64) {
+        let n1 = self.n;
+        self.n += 1.0;
+        let n = self.n;
+
+        let delta = x - self.mean;
+        let delta_n = delta / n;
+        let delta_n2 = delta_n * delta_n;
+        let term = delta * delta_n * n1;
+
+        self.mean += delta_n;
+        self.m4 += term * delta_n2 * (n * n - 3.0 * n + 3.0) + 6.0 * delta_n2 * self.m2
+            - 4.0 * delta_n * self.m3;
+        self.m3 += term * delta_n * (n - 2.0) - 3.0 * delta_n * self.m2;
+        self.m2 += term;
+    }
+
+    /// Sample variance.
+    fn variance(&self) -> Option<f64> {
+        (self.n >= 2.0).then(|| self.m2 / (self.n - 1.0))
+    }
+
+    /// Adjusted Fisher-Pearson skewness, as reported by pandas.
+    fn skewness(&self) -> Option<f64> {
+        let n = self.n;
+        if n < 3.0 || self.m2 == 0.0 {
+            return None;
+        }
+        let g1 = n.sqrt() * self.m3 / self.m2.powf(1.5);
+        Some(g1 * (n * (n - 1.0)).sqrt() / (n - 2.0))
+    }
+
+    /// Sample excess kurtosis, as reported by pandas.
+    fn kurtosis(&self) -> Option<f64> {
+        let n = self.n;
+        if n < 4.0 || self.m2 == 0.0 {
+            return None;
+        }
+        let g2 = n * self.m4 / (self.m2 * self.m2) - 3.0;
+        Some(((n + 1.0) * g2 + 6.0) * (n - 1.0) / ((n - 2.0) * (n - 3.0)))
+    }
+}
+
+/// Every statistic of [`DESCRIBE_STATISTICS`] for the columns at `indices`.
+///
+/// The result holds one row per statistic with one value per column. Nulls
+/// are counted in `null_count` and skipped everywhere else. Percentiles are
+/// interpolated linearly and use an external sort like [`median`].
+pub fn describe<I, R>(rows: I, indices: &[usize]) -> Result<Vec<Vec<Value>>, String>
+where
+    I: Iterator<Item = Result<R, String>>,
+    R: AsRef<[Value]>,
+{
+    let mut accumulators = vec![Accumulator::default(); indices.len()];
+    let mut moments = vec![Moments::default(); indices.len()];
+    let mut sorters: Vec<_> = indices
+        .iter()
+        .map(|_| ExternalSorter::new(SORT_CHUNK_ROWS, cmp_first))
+        .collect();
+
+    for row in rows {
+        let row = row?;
+        for (i, &idx) in indices.iter().enumerate() {
+            let value = &row.as_ref()[idx];
+            accumulators[i].push(value);
+            if let Some(num) = value.as_f64() {
+                moments[i].push(num);
+                sorters[i].push(vec![Value::Float(num)])?;
+            }
+        }
+    }
+
+    let float = |value: Option<f64>| value.map_or(Value::Null, Value::Float);
+    let mut columns = Vec::new();
+    for ((acc, moments), sorter) in accumulators.iter().zip(moments).zip(sorters) {
+        let variance = moments.variance();
+        let quantiles = sorted_quantiles(sorter, &[0.25, 0.5, 0.75], QuantileMethod::Linear)?;
+        let mut column = vec![
+            acc.finish(Aggregate::Count, false),
+            Value::Int(acc.nulls() as i64),
+            acc.finish(Aggregate::Min, false),
+            acc.finish(Aggregate::Max, false),
+            acc.finish(Aggregate::Sum, false),
+            acc.finish(Aggregate::Mean, false),
+            float(variance.map(f64::sqrt)),
+            float(variance),
+        ];
+        column.extend(quantiles);
+        column.push(float(moments.skewness()));
+        column.push(float(moments.kurtosis()));
+        columns.push(column);
+    }
+
+    Ok((0..DESCRIBE_STATISTICS.len())
+        .map(|stat| columns.iter().map(|column| column[stat].clone()).collect())
+        .collect())
+}
+
+/// Most bins of a histogram, so that a few outliers cannot make a rule pick
+/// millions of them.
+pub const MAX_BINS: usize = 1000;
+
+/// Rule picking the number of histogram bins from the data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum BinRule {
+    /// log2(n) + 1 bins
+    #[default]
+    Sturges,
+    /// Square root of n bins
+    Sqrt,
+    /// 2 * n^(1/3) bins
+    Rice,
+    /// Bins 3.49 standard deviations wide, divided by n^(1/3)
+    Scott,
+    /// Bins twice the interquartile range wide, divided by n^(1/3)
+    FreedmanDiaconis,
+}
+
+impl BinRule {
+    /// Number of bins for `values`, which must be sorted ascending, at most
+    /// [`MAX_BINS`].
+    pub fn bins(self, values: &[f64]) -> usize {
+        let n = values.len() as f64;
+        let (Some(min), Some(max)) = (values.first(), values.last()) else {
+            return 1;
+        };
+        let width = match self {
+            Self::Sturges => return (n.log2().ceil() as usize + 1).min(MAX_BINS),
+            Self::Sqrt => return (n.sqrt().ceil() as usize).min(MAX_BINS),
+            Self::Rice => return ((2.0 * n.cbrt()).ceil() as usize).min(MAX_BINS),
+            Self::Scott => {
+                let mut moments = Moments::default();
+                values.iter().for_each(|&value| moments.push(value));
+                3.49 * moments.variance().unwrap_or(0.0).sqrt() / n.cbrt()
+            }
+            Self::FreedmanDiaconis => {
+                let quartile = |q: f64| {
+                    let rank = q * (n - 1.0);
+                    let (lower, upper) =
+                        (values[rank.floor() as usize], values[rank.ceil() as usize]);
+                    QuantileMethod::Linear.interpolate(rank, lower, upper)
+                };
+                2.0 * (quartile(0.75) - quartile(0.25)) / n.cbrt()
+            }
+        };
+        if width > 0.0 {
+            (((max - min) / width).ceil() as usize).clamp(1, MAX_BINS)
+        } else {
+            1
+        }
+    }
+}
+
+/// Range of values counted by one histogram bin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bin {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize,
+}
+
+/// Count `values` into `bins` bins of equal width spanning their range. Each
+/// bin includes its start, and the last one its end too.
+pub fn histogram(values: &[f64], bins: usize) -> Vec<Bin> {
+    let (min, max) = values
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &value| {
+            (min.min(value), max.max(value))
+        });
+    if values.is_empty() || bins == 0 {
+        return Vec::new();
+    }
+    if min == max {
+        return vec![Bin {
+            start: min,
+            end: max,
+            count: values.len(),
+        }];
+    }
+
+    let width = (max - min) / bins as f64;
+    let mut histogram: Vec<Bin> = (0..bins)
+        .map(|i| Bin {
+            start: min + width * i as f64,
+            end: if i + 1 == bins {
+                max
+            } else {
+                min + width * (i + 1) as f64
+            },
+            count: 0,
+        })
+        .collect();
+    for &value in values {
+        let bin = (((value - min) / width) as usize).min(bins - 1);
+        histogram[bin].count += 1;
+    }
+    histogram
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(values: &[Value]) -> impl Iterator<Item = Result<Vec<Value>, String>> {
+        values.iter().cloned().map(|value| Ok(vec![value]))
+    }
+
+    #[test]
+    fn test_nulls_are_skipped_unless_included() {
+        let values = [Value::Null, Value::Int(-1), Value::Int(5)];
+
+        let mean = |include_nulls| aggregate(column(&values), &[0], Aggregate::Mean, include_nulls);
+        assert_eq!(mean(false).unwrap(), vec![Value::Float(2.0)]);
+        assert_eq!(mean(true).unwrap(), vec![Value::Null]);
+
+        let count =
+            |include_nulls| aggregate(column(&values), &[0], Aggregate::Count, include_nulls);
+        assert_eq!(count(false).unwrap(), vec![Value::Int(2)]);
+        assert_eq!(count(true).unwrap(), vec![Value::Int(3)]);
+
+        assert_eq!(
+            median(column(&values), &[0], QuantileMethod::Linear, false).unwrap(),
+            vec![Value::Float(2.0)]
+        );
+        assert_eq!(
+            median(column(&values), &[0], QuantileMethod::Linear, true).unwrap(),
+            vec![Value::Null]
+        );
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let values = [4.0, -2.0, 7.0, 1.0].map(Value::Float);
+        let run = |agg| aggregate(column(&values), &[0], agg, false).unwrap()[0].clone();
+
+        assert_eq!(run(Aggregate::Sum), Value::Float(10.0));
+        assert_eq!(run(Aggregate::Min), Value::Float(-2.0));
+        assert_eq!(run(Aggregate::Max), Value::Float(7.0));
+        assert_eq!(
+            aggregate(column(&[]), &[0], Aggregate::Mean, false).unwrap(),
+            vec![Value::Null]
+        );
+    }
+
+    #[test]
+    fn test_streaming_aggregates() {
+        let values = [4.0, -2.0, 7.0, 1.0].map(Value::Float);
+        let mut acc = Accumulator::default();
+        values.iter().for_each(|value| acc.push(value));
+
+        assert_eq!(acc.finish(Aggregate::Sum, false), Value::Float(10.0));
+        assert_eq!(acc.finish(Aggregate::Min, false), Value::Float(-2.0));
+        assert_eq!(acc.finish(Aggregate::Max, false), Value::Float(7.0));
+        assert_eq!(
+            Accumulator::default().finish(Aggregate::Mean, false),
+            Value::Null
+        );
+
+        let mut ints = Accumulator::default();
+        [3, 9, -4]
+            .map(Value::Int)
+            .iter()
+            .for_each(|value| ints.push(value));
+        assert_eq!(ints.finish(Aggregate::Sum, false), Value::Int(8));
+        assert_eq!(ints.finish(Aggregate::Min, false), Value::Int(-4));
+        assert_eq!(ints.finish(Aggregate::Max, false), Value::Int(9));
+        ints.push(&Value::Float(0.5));
+        assert_eq!(ints.finish(Aggregate::Sum, false), Value::Float(8.5));
+
+        // Past 2^53 integers are exact only as integers.
+        let mut ids = Accumulator::default();
+        ids.push(&Value::Int(9_007_199_254_740_995));
+        ids.push(&Value::Int(9_007_199_254_740_993));
+        assert_eq!(
+            ids.finish(Aggregate::Min, false),
+            Value::Int(9_007_199_254_740_993)
+        );
+        assert_eq!(
+            ids.finish(Aggregate::Max, false),
+            Value::Int(9_007_199_254_740_995)
+        );
+        assert_eq!(
+            Accumulator::default().finish(Aggregate::Sum, false),
+            Value::Null
+        );
+
+        let mut sorter = ExternalSorter::new(3, cmp_first);
+        for value in values {
+            sorter.push(vec![value]).unwrap();
+        }
+        assert_eq!(
+            sorted_quantiles(sorter, &[0.5], QuantileMethod::Linear).unwrap()[0],
+            Value::Float(2.5)
+        );
+    }
+
+    #[test]
+    fn test_median_across_spilled_runs() {
+        let mut sorter = ExternalSorter::new(2, cmp_first);
+        for value in [9.0, 1.0, 5.0, 3.0, 7.0, 2.0] {
+            sorter.push(vec![Value::Float(value)]).unwrap();
+        }
+        assert_eq!(
+            sorted_quantiles(sorter, &[0.5], QuantileMethod::Linear).unwrap()[0],
+            Value::Float(4.0)
+        );
+    }
+
+    #[test]
+    fn test_quantile_methods() {
+        let values = [4.0, 1.0, 5.0, 3.0, 2.0].map(|v| Ok(vec![Value::Float(v)]));
+        let at = |qs: &[f64], method| {
+            quantiles(values.clone().into_iter(), &[0], qs, method, false)
+                .unwrap()
+                .into_iter()
+                .map(|row| row[0].as_f64().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        // Ranks 0.5, 2, 2.5 and 3.5 of the sorted values 1 to 5.
+        let qs = [0.125, 0.5, 0.625, 0.875];
+        assert_eq!(at(&qs, QuantileMethod::Linear), [1.5, 3.0, 3.5, 4.5]);
+        assert_eq!(at(&qs, QuantileMethod::Lower), [1.0, 3.0, 3.0, 4.0]);
+        assert_eq!(at(&qs, QuantileMethod::Higher), [2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(at(&qs, QuantileMethod::Nearest), [1.0, 3.0, 3.0, 5.0]);
+        assert_eq!(at(&qs, QuantileMethod::Midpoint), [1.5, 3.0, 3.5, 4.5]);
+        assert_eq!(at(&[0.0, 1.0], QuantileMethod::Midpoint), [1.0, 5.0]);
+
+        assert!(
+            quantiles(
+                values.into_iter(),
+                &[0],
+                &[1.01],
+                QuantileMethod::Linear,
+                false
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_describe() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0].map(Value::Float);
+        let column = values.into_iter().chain([Value::Null]);
+        let stats = describe(column.map(|value| Ok(vec![value])), &[0]).unwrap();
+        let stat = |name| {
+            let idx = DESCRIBE_STATISTICS.iter().position(|s| *s == name).unwrap();
+            stats[idx][0].clone()
+        };
+        let close = |name, expected: f64| {
+            let value = stat(name).as_f64().unwrap();
+            assert!(
+                (value - expected).abs() < 1e-9,
+                "{name}: {value} != {expected}"
+            );
+        };
+
+        assert_eq!(stat("count"), Value::Int(8));
+        assert_eq!(stat("null_count"), Value::Int(1));
+        assert_eq!(stat("min"), Value::Float(2.0));
+        assert_eq!(stat("max"), Value::Float(9.0));
+        assert_eq!(stat("sum"), Value::Float(40.0));
+        close("mean", 5.0);
+        close("var", 32.0 / 7.0);
+        close("std", (32.0f64 / 7.0).sqrt());
+        close("p25", 4.0);
+        close("p50", 4.5);
+        close("p75", 5.5);
+        // Reference values from pandas `Series.skew()` and `Series.kurt()`.
+        close("skewness", 0.8184875533567997);
+        close("kurtosis", 0.940625);
+
+        let stats = describe(std::iter::once(Ok(vec![Value::Int(3)])), &[0]).unwrap();
+        assert_eq!(stats[6][0], Value::Null);
+        assert_eq!(stats[9][0], Value::Float(3.0));
+    }
+
+    #[test]
+    fn test_histogram_bins() {
+        let values: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert_eq!(BinRule::Sturges.bins(&values), 5);
+        assert_eq!(BinRule::Sqrt.bins(&values), 4);
+        assert_eq!(BinRule::Rice.bins(&values), 5);
+        assert_eq!(BinRule::FreedmanDiaconis.bins(&values), 3);
+        assert_eq!(BinRule::Scott.bins(&[2.0, 2.0]), 1);
+        // One far outlier would ask for millions of narrow bins.
+        let mut outlier = values.clone();
+        outlier.push(1e9);
+        assert_eq!(BinRule::FreedmanDiaconis.bins(&outlier), MAX_BINS);
+
+        let counts: Vec<usize> = histogram(&values, 3).iter().map(|bin| bin.count).collect();
+        assert_eq!(counts, [3, 3, 4]);
+        assert_eq!(histogram(&values, 3)[2].end, 10.0);
+        assert_eq!(histogram(&[5.0, 5.0], 4).len(), 1);
+        assert!(histogram(&[], 4).is_empty());
+    }
+}
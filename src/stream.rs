@@ -0,0 +1,235 @@
+use std::{cell::RefCell, io::BufRead, iter::Peekable, rc::Rc, vec};
+
+use crate::{
+    csv::{CsvReader, Dialect},
+    dataset::RowStream,
+    value::{ColumnType, Value},
+};
+
+/// Records read before the column types of a stream are fixed.
+pub const TYPE_SAMPLE_ROWS: usize = 1000;
+
+/// Data records of a csv input, checked against the header count.
+pub struct Records<R: BufRead> {
+    reader: Peekable<CsvReader<R>>,
+    columns: usize,
+    count: usize,
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = Result<Vec<String>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = match self.reader.next()? {
+            Ok(record) => record,
+            Err(err) => return Some(Err(format!("Read record failed: {err}"))),
+        };
+
+        self.count += 1;
+        if record.len() != self.columns {
+            return Some(Err(format!(
+                "Mismatch between header count and data columns on record {}",
+                self.count
+            )));
+        }
+        Some(Ok(record))
+    }
+}
+
+/// Read the header line of a csv input and return it with the remaining records.
+/// With `no_header` the columns are named `column1`, `column2`, ...
+pub fn open<R: BufRead>(
+    reader: R,
+    dialect: Dialect,
+    no_header: bool,
+) -> Result<(Vec<String>, Records<R>), String> {
+    let mut reader = CsvReader::new(reader, dialect).peekable();
+
+    let headers = if no_header {
+        let columns = match reader.peek() {
+            Some(Ok(record)) => record.len(),
+            Some(Err(_)) => 0,
+            None => return Err("Missing headers".to_string()),
+        };
+        (1..=columns).map(|i| format!("column{i}")).collect()
+    } else {
+        reader
+            .next()
+            .ok_or_else(|| "Missing headers".to_string())?
+            .map_err(|err| format!("Read headers failed: {err}"))?
+            .iter()
+            .map(|s| s.trim().to_lowercase())
+            .collect::<Vec<String>>()
+    };
+
+    let records = Records {
+        reader,
+        columns: headers.len(),
+        count: 0,
+    };
+    Ok((headers, records))
+}
+
+/// Records converted to typed rows using column types inferred from a sample.
+///
+/// Cells that do not fit the type of their column are read as nulls and
+/// counted in [`TypedRecords::unfit_cells`].
+pub struct TypedRecords<I> {
+    types: Vec<ColumnType>,
+    sample: vec::IntoIter<Vec<String>>,
+    records: I,
+    unfit: UnfitCells,
+}
+
+impl<I> TypedRecords<I> {
+    /// Counts of the cells read as nulls so far, which keep growing as the
+    /// rows are read.
+    pub fn unfit_cells(&self) -> UnfitCells {
+        self.unfit.clone()
+    }
+}
+
+/// Number of cells of each column that did not fit its type, shared by
+/// clones.
+#[derive(Debug, Clone, Default)]
+pub struct UnfitCells(Rc<RefCell<Vec<usize>>>);
+
+impl UnfitCells {
+    pub fn counts(&self) -> Vec<usize> {
+        self.0.borrow().clone()
+    }
+
+    /// One line for each column with unfit cells, e.g. `Column 'price': 3
+    /// non-float cells set to null`.
+    pub fn report(&self, headers: &[String], types: &[ColumnType]) -> Vec<String> {
+        self.counts()
+            .iter()
+            .zip(headers.iter().zip(types))
+            .filter(|(count, _)| **count > 0)
+            .map(|(count, (header, ty))| {
+                let cells = if *count == 1 { "cell" } else { "cells" };
+                format!("Column '{header}': {count} non-{ty} {cells} set to null")
+            })
+            .collect()
+    }
+}
+
+impl<I> Iterator for TypedRecords<I>
+where
+    I: Iterator<Item = Result<Vec<String>, String>>,
+{
+    type Item = Result<Vec<Value>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = match self.sample.next() {
+            Some(record) => record,
+            None => match self.records.next()? {
+                Ok(record) => record,
+                Err(err) => return Some(Err(err)),
+            },
+        };
+
+        let mut unfit = self.unfit.0.borrow_mut();
+        Some(Ok(record
+            .iter()
+            .zip(&self.types)
+            .enumerate()
+            .map(|(idx, (cell, ty))| {
+                ty.try_parse(cell).unwrap_or_else(|| {
+                    unfit[idx] += 1;
+                    Value::Null
+                })
+            })
+            .collect()))
+    }
+}
+
+/// Stream typed rows, inferring column types from the first `sample_rows` records.
+pub fn typed<I>(
+    headers: Vec<String>,
+    mut records: I,
+    sample_rows: usize,
+) -> Result<RowStream<TypedRecords<I>>, String>
+where
+    I: Iterator<Item = Result<Vec<String>, String>>,
+{
+    let sample = records
+        .by_ref()
+        .take(sample_rows)
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let types = (0..headers.len())
+        .map(|idx| ColumnType::infer(sample.iter().map(|record| record[idx].as_str())))
+        .collect::<Vec<_>>();
+
+    let unfit = UnfitCells(Rc::new(RefCell::new(vec![0; headers.len()])));
+    Ok(RowStream {
+        headers,
+        types: types.clone(),
+        rows: TypedRecords {
+            types,
+            sample: sample.into_iter(),
+            records,
+            unfit,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_and_stream_rows() {
+        let input = "ID,Price\n1,2\n2,\n3,4.5\n";
+        let (headers, records) = open(input.as_bytes(), Dialect::default(), false).unwrap();
+        assert_eq!(headers, vec!["id", "price"]);
+
+        let stream = typed(headers, records, 2).unwrap();
+        assert_eq!(stream.types, vec![ColumnType::Int, ColumnType::Int]);
+
+        let rows = stream.rows.collect::<Result<Vec<_>, String>>().unwrap();
+        assert_eq!(rows[1][1], Value::Null);
+        // Past the sample, floats in an integer column are widened.
+        assert_eq!(rows[2][1], Value::Float(4.5));
+    }
+
+    #[test]
+    fn test_unfit_cells_are_counted() {
+        let input = "id,price\n1,2\n2,3\nx,n/a\n4,?\n";
+        let (headers, records) = open(input.as_bytes(), Dialect::default(), false).unwrap();
+        let stream = typed(headers, records, 2).unwrap();
+        let unfit = stream.rows.unfit_cells();
+
+        let rows = stream.rows.collect::<Result<Vec<_>, String>>().unwrap();
+        assert_eq!(rows[2], vec![Value::Null, Value::Null]);
+        assert_eq!(rows[3], vec![Value::Int(4), Value::Null]);
+        assert_eq!(unfit.counts(), vec![1, 2]);
+        assert_eq!(
+            unfit.report(&stream.headers, &stream.types),
+            vec![
+                "Column 'id': 1 non-integer cell set to null",
+                "Column 'price': 2 non-integer cells set to null",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_record_count_mismatch() {
+        let input = "a,b\n1,2\n3\n";
+        let (_, records) = open(input.as_bytes(), Dialect::default(), false).unwrap();
+        let result = records.collect::<Result<Vec<_>, String>>();
+        assert_eq!(
+            result.unwrap_err(),
+            "Mismatch between header count and data columns on record 2"
+        );
+    }
+
+    #[test]
+    fn test_no_header() {
+        let input = "1,2\n3,4\n";
+        let (headers, records) = open(input.as_bytes(), Dialect::default(), true).unwrap();
+        assert_eq!(headers, vec!["column1", "column2"]);
+        assert_eq!(records.count(), 2);
+    }
+}
@@ -0,0 +1,374 @@
+use std::fmt::Write;
+
+use crate::chart::{Axis, Chart, Plot};
+
+/// Size in pixels of exported charts.
+pub const SVG_WIDTH: u32 = 800;
+pub const SVG_HEIGHT: u32 = 500;
+
+/// Stroke and fill of each series, in order.
+const PALETTE: [&str; 8] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#17becf",
+];
+
+/// Room around the plot area for the title, tick labels and axis names.
+const MARGIN_TOP: f64 = 50.0;
+const MARGIN_BOTTOM: f64 = 60.0;
+const MARGIN_LEFT: f64 = 80.0;
+const MARGIN_RIGHT: f64 = 30.0;
+
+/// Ticks aimed for along each axis.
+const X_TICKS: usize = 8;
+const Y_TICKS: usize = 6;
+
+const FONT_SIZE: f64 = 12.0;
+const TITLE_SIZE: f64 = 16.0;
+
+/// Plot area of a chart, in pixels from the top left corner.
+struct Frame {
+    left: f64,
+    top: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Frame {
+    fn right(&self) -> f64 {
+        self.left + self.width
+    }
+
+    fn bottom(&self) -> f64 {
+        self.top + self.height
+    }
+
+    fn x(&self, axis: Axis, value: f64) -> f64 {
+        self.left + axis.fraction(value) * self.width
+    }
+
+    fn y(&self, axis: Axis, value: f64) -> f64 {
+        self.bottom() - axis.fraction(value) * self.height
+    }
+}
+
+/// Draw `chart` as a standalone svg document of `width` by `height` pixels,
+/// with its axes, tick labels, gridlines, title and legend.
+pub fn render(chart: &Chart, width: u32, height: u32) -> String {
+    let (w, h) = (f64::from(width), f64::from(height));
+    let right_margin = match chart.secondary_axis {
+        Some(_) => MARGIN_LEFT,
+        None => MARGIN_RIGHT,
+    };
+    let frame = Frame {
+        left: MARGIN_LEFT,
+        top: MARGIN_TOP,
+        width: (w - MARGIN_LEFT - right_margin).max(1.0),
+        height: (h - MARGIN_TOP - MARGIN_BOTTOM).max(1.0),
+    };
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif" font-size="{FONT_SIZE}">"#
+    );
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+
+    let mut title = chart.y_label.clone();
+    if let Some(label) = &chart.secondary_label {
+        title.push_str(&format!(" and {label}"));
+    }
+    title.push_str(&format!(" by {}", chart.x_label));
+    text(
+        &mut svg,
+        w / 2.0,
+        MARGIN_TOP / 2.0 + 5.0,
+        "middle",
+        TITLE_SIZE,
+        &title,
+    );
+
+    y_ticks(&mut svg, &frame, chart.y_axis, false);
+    if let Some(axis) = chart.secondary_axis {
+        y_ticks(&mut svg, &frame, axis, true);
+    }
+
+    match &chart.plot {
+        Plot::Points {
+            series,
+            joined,
+            x_axis,
+        } => {
+            x_ticks(&mut svg, &frame, *x_axis);
+            for (i, series) in series.iter().enumerate() {
+                let color = PALETTE[i % PALETTE.len()];
+                let y_axis = chart.series_axis(series);
+                let points: Vec<(f64, f64)> = series
+                    .points
+                    .iter()
+                    .map(|&(x, y)| (frame.x(*x_axis, x), frame.y(y_axis, y)))
+                    .collect();
+                if *joined {
+                    let coordinates: Vec<String> = points
+                        .iter()
+                        .map(|(x, y)| format!("{x:.1},{y:.1}"))
+                        .collect();
+                    let _ = writeln!(
+                        svg,
+                        r#"<polyline points="{}" fill="none" stroke="{color}" stroke-width="2"/>"#,
+                        coordinates.join(" ")
+                    );
+                } else {
+                    for (x, y) in points {
+                        let _ = writeln!(
+                            svg,
+                            r#"<circle cx="{x:.1}" cy="{y:.1}" r="3" fill="{color}"/>"#
+                        );
+                    }
+                }
+            }
+        }
+        Plot::Bars(bars) => {
+            let band = frame.width / bars.len() as f64;
+            let zero = frame.y(chart.y_axis, 0.0);
+            for (i, (label, value)) in bars.iter().enumerate() {
+                let center = frame.left + band * (i as f64 + 0.5);
+                if let Some(value) = value {
+                    let top = frame.y(chart.y_axis, *value);
+                    rect(
+                        &mut svg,
+                        center - band * 0.4,
+                        top.min(zero),
+                        band * 0.8,
+                        (top - zero).abs(),
+                    );
+                }
+                text(
+                    &mut svg,
+                    center,
+                    frame.bottom() + 18.0,
+                    "middle",
+                    FONT_SIZE,
+                    label,
+                );
+            }
+        }
+        Plot::Histogram { bins, x_axis } => {
+            x_ticks(&mut svg, &frame, *x_axis);
+            let zero = frame.y(chart.y_axis, 0.0);
+            for bin in bins {
+                let (start, end) = (frame.x(*x_axis, bin.start), frame.x(*x_axis, bin.end));
+                let top = frame.y(chart.y_axis, bin.count as f64);
+                rect(&mut svg, start, top, end - start, zero - top);
+            }
+        }
+    }
+
+    let _ = writeln!(
+        svg,
+        r##"<path d="M{left},{top} V{bottom} H{right}" fill="none" stroke="#333"/>"##,
+        left = frame.left,
+        top = frame.top,
+        bottom = frame.bottom(),
+        right = frame.right(),
+    );
+    if chart.secondary_axis.is_some() {
+        line(
+            &mut svg,
+            (frame.right(), frame.top),
+            (frame.right(), frame.bottom()),
+            "#333",
+        );
+    }
+
+    text(
+        &mut svg,
+        frame.left + frame.width / 2.0,
+        h - 15.0,
+        "middle",
+        FONT_SIZE,
+        &chart.x_label,
+    );
+    rotated_text(
+        &mut svg,
+        20.0,
+        frame.top + frame.height / 2.0,
+        -90,
+        &chart.y_label,
+    );
+    if let Some(label) = &chart.secondary_label {
+        rotated_text(
+            &mut svg,
+            w - 20.0,
+            frame.top + frame.height / 2.0,
+            90,
+            label,
+        );
+    }
+
+    legend(&mut svg, &frame, &chart.legend());
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Horizontal gridlines and labels of the y axis, or of the secondary axis
+/// on the right without gridlines.
+fn y_ticks(svg: &mut String, frame: &Frame, axis: Axis, secondary: bool) {
+    let ticks = axis.ticks(Y_TICKS);
+    for &tick in &ticks {
+        let y = frame.y(axis, tick);
+        let label = tick_label(tick, &ticks);
+        if secondary {
+            text(
+                svg,
+                frame.right() + 8.0,
+                y + 4.0,
+                "start",
+                FONT_SIZE,
+                &label,
+            );
+        } else {
+            line(svg, (frame.left, y), (frame.right(), y), "#e0e0e0");
+            text(svg, frame.left - 8.0, y + 4.0, "end", FONT_SIZE, &label);
+        }
+    }
+}
+
+/// Vertical gridlines and labels of a numeric x axis.
+fn x_ticks(svg: &mut String, frame: &Frame, axis: Axis) {
+    let ticks = axis.ticks(X_TICKS);
+    for &tick in &ticks {
+        let x = frame.x(axis, tick);
+        line(svg, (x, frame.top), (x, frame.bottom()), "#e0e0e0");
+        let label = tick_label(tick, &ticks);
+        text(svg, x, frame.bottom() + 18.0, "middle", FONT_SIZE, &label);
+    }
+}
+
+/// Names of the series in the top right corner of the plot, each after a
+/// stroke of its color.
+fn legend(svg: &mut String, frame: &Frame, names: &[String]) {
+    if names.is_empty() {
+        return;
+    }
+    let longest = names
+        .iter()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let width = longest as f64 * FONT_SIZE * 0.6 + 40.0;
+    let (left, top) = (frame.right() - width - 10.0, frame.top + 10.0);
+    let _ = writeln!(
+        svg,
+        r##"<rect x="{left:.1}" y="{top:.1}" width="{width:.1}" height="{:.1}" fill="white" fill-opacity="0.8" stroke="#ccc"/>"##,
+        names.len() as f64 * 18.0 + 8.0
+    );
+    for (i, name) in names.iter().enumerate() {
+        let y = top + 15.0 + i as f64 * 18.0;
+        let color = PALETTE[i % PALETTE.len()];
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{color}" stroke-width="2"/>"#,
+            left + 8.0,
+            y - 4.0,
+            left + 28.0,
+            y - 4.0
+        );
+        text(svg, left + 34.0, y, "start", FONT_SIZE, name);
+    }
+}
+
+/// `value` with as many decimals as the spacing of `ticks` needs.
+fn tick_label(value: f64, ticks: &[f64]) -> String {
+    let step = match ticks {
+        [first, second, ..] => second - first,
+        _ => value.abs(),
+    };
+    let decimals = if step > 0.0 {
+        (-step.log10().floor()).max(0.0) as usize
+    } else {
+        0
+    };
+    // Adding zero turns -0 into 0.
+    format!("{:.decimals$}", value + 0.0)
+}
+
+fn line(svg: &mut String, from: (f64, f64), to: (f64, f64), color: &str) {
+    let _ = writeln!(
+        svg,
+        r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{color}"/>"#,
+        from.0, from.1, to.0, to.1
+    );
+}
+
+fn rect(svg: &mut String, x: f64, y: f64, width: f64, height: f64) {
+    let _ = writeln!(
+        svg,
+        r#"<rect x="{x:.1}" y="{y:.1}" width="{width:.1}" height="{height:.1}" fill="{}" stroke="white"/>"#,
+        PALETTE[0]
+    );
+}
+
+fn text(svg: &mut String, x: f64, y: f64, anchor: &str, size: f64, content: &str) {
+    let _ = writeln!(
+        svg,
+        r#"<text x="{x:.1}" y="{y:.1}" text-anchor="{anchor}" font-size="{size}">{}</text>"#,
+        escape(content)
+    );
+}
+
+fn rotated_text(svg: &mut String, x: f64, y: f64, angle: i32, content: &str) {
+    let _ = writeln!(
+        svg,
+        r#"<text x="{x:.1}" y="{y:.1}" text-anchor="middle" transform="rotate({angle} {x:.1} {y:.1})">{}</text>"#,
+        escape(content)
+    );
+}
+
+/// `text` with the characters svg and html reserve replaced by entities.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dataset, stats::BinRule};
+
+    fn dataset() -> Dataset {
+        let records = [["1", "10", "2"], ["2", "20", "4"], ["3", "15", "6"]]
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect();
+        let headers = ["id", "price", "r&d"].map(String::from).to_vec();
+        Dataset::from_records(headers, records).unwrap()
+    }
+
+    #[test]
+    fn test_line_svg() {
+        let chart = Chart::line(&dataset(), "id", &["price", "r&d"], &[]).unwrap();
+        let svg = render(&chart, 400, 300);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"400\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        // The first point sits at the left of the plot area, the lowest
+        // price at its bottom.
+        assert!(svg.contains("points=\"80.0,240.0 "));
+        assert!(svg.contains(">price, r&amp;d by id</text>"));
+        assert!(svg.contains(">15</text>"));
+        assert!(svg.contains("stroke=\"#e0e0e0\""));
+    }
+
+    #[test]
+    fn test_bars_and_ticks() {
+        let chart = Chart::histogram(&dataset(), "price", Some(2), BinRule::Sturges).unwrap();
+        let svg = render(&chart, 400, 300);
+        assert_eq!(svg.matches("fill=\"#1f77b4\"").count(), 2);
+
+        let axis = Axis { min: 0.0, max: 1.0 };
+        assert_eq!(axis.ticks(5), [0.0, 0.2, 0.4, 0.6000000000000001, 0.8, 1.0]);
+        assert_eq!(tick_label(0.6000000000000001, &axis.ticks(5)), "0.6");
+        assert_eq!(tick_label(-0.0, &[0.0, 50.0]), "0");
+    }
+}
@@ -0,0 +1,296 @@
+use std::{cmp::Ordering, fmt};
+
+use serde::{Serialize, Serializer};
+
+/// Calendar date in `YYYY-MM-DD` form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    year: i32,
+    month: u8,
+    day: u8,
+}
+
+impl Date {
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.splitn(3, '-');
+        let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+        if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            return None;
+        }
+
+        let year = year.parse::<i32>().ok()?;
+        let month = month.parse::<u8>().ok()?;
+        let day = day.parse::<u8>().ok()?;
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+
+        Some(Self { year, month, day })
+    }
+}
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// The type of a column, inferred from its cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Int,
+    Float,
+    Bool,
+    Date,
+    Str,
+}
+
+/// Share of the non-empty cells of a column that may not fit its inferred
+/// type.
+pub const UNFIT_TOLERANCE: f64 = 0.05;
+
+impl ColumnType {
+    /// Pick the type most non-empty cells fit into, the narrowest on a tie.
+    /// Up to [`UNFIT_TOLERANCE`] of the cells may not fit, like a stray `n/a`
+    /// in a number column; they parse as nulls. Columns with no non-empty
+    /// cells, or too many cells that fit no type, are treated as strings.
+    pub fn infer<'a>(cells: impl IntoIterator<Item = &'a str>) -> Self {
+        const CANDIDATES: [ColumnType; 4] = [
+            ColumnType::Int,
+            ColumnType::Float,
+            ColumnType::Bool,
+            ColumnType::Date,
+        ];
+        let mut unfit = [0usize; CANDIDATES.len()];
+        let mut seen = 0usize;
+
+        for cell in cells {
+            let cell = cell.trim();
+            if cell.is_empty() {
+                continue;
+            }
+            seen += 1;
+            for (i, ty) in CANDIDATES.iter().enumerate() {
+                if !ty.fits(cell) {
+                    unfit[i] += 1;
+                }
+            }
+        }
+
+        CANDIDATES
+            .into_iter()
+            .zip(unfit)
+            .min_by_key(|&(_, unfit)| unfit)
+            .filter(|&(_, unfit)| seen > 0 && unfit as f64 <= seen as f64 * UNFIT_TOLERANCE)
+            .map_or(Self::Str, |(ty, _)| ty)
+    }
+
+    fn fits(&self, cell: &str) -> bool {
+        match self {
+            Self::Int => cell.parse::<i64>().is_ok(),
+            Self::Float => cell.parse::<f64>().is_ok(),
+            Self::Bool => parse_bool(cell).is_some(),
+            Self::Date => Date::parse(cell).is_some(),
+            Self::Str => true,
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Self::Int | Self::Float)
+    }
+
+    /// Convert a raw cell into a value of this type.
+    /// Empty cells and cells that do not fit the column type become nulls.
+    pub fn parse(&self, cell: &str) -> Value {
+        self.try_parse(cell).unwrap_or(Value::Null)
+    }
+
+    /// Like [`ColumnType::parse`], but `None` for a cell that does not fit
+    /// the column type. Empty cells are nulls and text cells are trimmed.
+    pub fn try_parse(&self, cell: &str) -> Option<Value> {
+        let cell = cell.trim();
+        if cell.is_empty() {
+            return Some(Value::Null);
+        }
+
+        match self {
+            // Columns typed from a sample may still meet a float later on.
+            Self::Int => cell
+                .parse()
+                .ok()
+                .map(Value::Int)
+                .or_else(|| cell.parse().ok().map(Value::Float)),
+            Self::Float => cell.parse().ok().map(Value::Float),
+            Self::Bool => parse_bool(cell).map(Value::Bool),
+            Self::Date => Date::parse(cell).map(Value::Date),
+            Self::Str => Some(Value::Str(cell.to_string())),
+        }
+    }
+}
+
+fn parse_bool(cell: &str) -> Option<bool> {
+    if cell.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if cell.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Int => "integer",
+            Self::Float => "float",
+            Self::Bool => "boolean",
+            Self::Date => "date",
+            Self::Str => "string",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A single typed cell.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Date(Date),
+    Str(String),
+}
+
+impl Value {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Int(i) => Some(*i as f64),
+            Self::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Int(_) | Self::Float(_) => 0,
+            Self::Bool(_) => 1,
+            Self::Date(_) => 2,
+            Self::Str(_) => 3,
+            Self::Null => 4,
+        }
+    }
+
+    /// Total ordering across values: numbers compare numerically,
+    /// values of different kinds are grouped by kind and nulls sort last.
+    pub fn total_cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => a.cmp(b),
+            (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
+            (Self::Date(a), Self::Date(b)) => a.cmp(b),
+            (Self::Str(a), Self::Str(b)) => a.cmp(b),
+            (a, b) => match (a.as_f64(), b.as_f64()) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                _ => a.rank().cmp(&b.rank()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => Ok(()),
+            Self::Int(i) => write!(f, "{i}"),
+            Self::Float(x) => write!(f, "{x}"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Date(d) => write!(f, "{d}"),
+            Self::Str(s) => f.write_str(s),
+        }
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Null => serializer.serialize_unit(),
+            Self::Int(i) => serializer.serialize_i64(*i),
+            Self::Float(x) => serializer.serialize_f64(*x),
+            Self::Bool(b) => serializer.serialize_bool(*b),
+            Self::Date(d) => serializer.collect_str(d),
+            Self::Str(s) => serializer.serialize_str(s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_column_types() {
+        assert_eq!(ColumnType::infer(["1", "2", ""]), ColumnType::Int);
+        assert_eq!(ColumnType::infer(["1", "2.5"]), ColumnType::Float);
+        assert_eq!(ColumnType::infer(["true", "FALSE"]), ColumnType::Bool);
+        assert_eq!(
+            ColumnType::infer(["2024-02-29", "2023-12-01"]),
+            ColumnType::Date
+        );
+        assert_eq!(ColumnType::infer(["2023-02-29"]), ColumnType::Str);
+        assert_eq!(ColumnType::infer(["1", "apple"]), ColumnType::Str);
+        assert_eq!(ColumnType::infer(["", ""]), ColumnType::Str);
+
+        // A few unfit cells leave the type alone, more make it text.
+        let mut cells = vec!["7"; 19];
+        cells.push("n/a");
+        assert_eq!(ColumnType::infer(cells.iter().copied()), ColumnType::Int);
+        cells.push("?");
+        assert_eq!(ColumnType::infer(cells.iter().copied()), ColumnType::Str);
+        cells.extend(["2.5"; 20]);
+        assert_eq!(ColumnType::infer(cells.iter().copied()), ColumnType::Float);
+    }
+
+    #[test]
+    fn test_value_ordering() {
+        assert_eq!(
+            Value::Int(2).total_cmp(&Value::Float(1.5)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            Value::Str("a".into()).total_cmp(&Value::Str("b".into())),
+            Ordering::Less
+        );
+        assert_eq!(
+            Value::Float(1e9).total_cmp(&Value::Str("a".into())),
+            Ordering::Less
+        );
+        assert_eq!(
+            Value::Null.total_cmp(&Value::Str("z".into())),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_parse_nulls() {
+        assert_eq!(ColumnType::Float.parse(" "), Value::Null);
+        assert_eq!(ColumnType::Int.parse("-1"), Value::Int(-1));
+        assert_eq!(ColumnType::Int.parse("2.5"), Value::Float(2.5));
+        assert_eq!(ColumnType::Int.parse("n/a"), Value::Null);
+        assert_eq!(ColumnType::Int.try_parse("n/a"), None);
+        assert_eq!(ColumnType::Str.parse(" n/a "), Value::Str("n/a".into()));
+        assert_eq!(serde_json::to_string(&Value::Null).unwrap(), "null");
+    }
+}